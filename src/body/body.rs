@@ -2,8 +2,12 @@ use std::borrow::Cow;
 #[cfg(feature = "stream")]
 use std::error::Error as StdError;
 use std::fmt;
+#[cfg(feature = "runtime")]
+use std::time::Duration;
 
 use bytes::Bytes;
+#[cfg(feature = "stream")]
+use bytes::BytesMut;
 use futures_channel::mpsc;
 use futures_channel::oneshot;
 use futures_core::Stream; // for mpsc::Receiver
@@ -11,6 +15,10 @@ use futures_core::Stream; // for mpsc::Receiver
 use futures_util::TryStreamExt;
 use http::HeaderMap;
 use http_body::{Body as HttpBody, SizeHint};
+#[cfg(feature = "stream")]
+use tokio::io::{AsyncRead, ReadBuf};
+#[cfg(feature = "runtime")]
+use tokio::time::{Instant, Sleep};
 
 use super::DecodedLength;
 #[cfg(feature = "stream")]
@@ -22,9 +30,13 @@ use crate::common::{task, watch, Pin, Poll};
 #[cfg(all(feature = "http2", any(feature = "client", feature = "server")))]
 use crate::proto::h2::ping;
 
+use super::tee::{self, TeeHalf};
+
 type BodySender = mpsc::Sender<Result<Bytes, crate::Error>>;
 type TrailersSender = oneshot::Sender<HeaderMap>;
 
+const DEFAULT_TEE_MAX_BUFFERED: usize = 64 * 1024;
+
 /// A stream of `Bytes`, used when receiving bodies.
 ///
 /// A good default [`HttpBody`](crate::body::HttpBody) to use in many
@@ -56,6 +68,7 @@ enum Kind {
     },
     #[cfg(feature = "ffi")]
     Ffi(crate::ffi::UserBody),
+    Tee(TeeHalf),
     #[cfg(feature = "stream")]
     Wrapped(
         SyncWrapper<
@@ -74,6 +87,23 @@ struct Extra {
     /// a brand new connection, since the pool didn't know about the idle
     /// connection yet.
     delayed_eof: Option<DelayEof>,
+    /// A chunk given back with `Body::push_back`, returned before polling
+    /// the inner body again.
+    pushed_back: Option<Bytes>,
+    /// Set by `Body::with_inactivity_timeout`.
+    #[cfg(feature = "runtime")]
+    inactivity: Option<Inactivity>,
+}
+
+#[cfg(feature = "runtime")]
+struct Inactivity {
+    duration: Duration,
+    /// Started lazily, the first time the body is pending, so that a
+    /// `Body` can be configured outside of a runtime.
+    sleep: Option<Pin<Box<Sleep>>>,
+    /// Set once the timeout error has been returned, after which the body
+    /// is ended.
+    timed_out: bool,
 }
 
 #[cfg(all(feature = "client", any(feature = "http1", feature = "http2")))]
@@ -197,6 +227,111 @@ impl Body {
         Body::new(Kind::Wrapped(SyncWrapper::new(Box::pin(mapped))))
     }
 
+    /// Wrap an `AsyncRead` in a `Body`, reading it in chunks of up to 8KB.
+    ///
+    /// The body ends when the reader reaches EOF. Its length is unknown, so
+    /// it is sent with chunked encoding in HTTP/1.1.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use hyper::Body;
+    /// let reader = std::io::Cursor::new(b"hello world".to_vec());
+    ///
+    /// let body = Body::from_async_read(reader);
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This function requires enabling the `stream` feature in your
+    /// `Cargo.toml`.
+    #[cfg(feature = "stream")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "stream")))]
+    pub fn from_async_read<R>(reader: R) -> Body
+    where
+        R: AsyncRead + Send + 'static,
+    {
+        Body::wrap_stream(ReaderStream {
+            reader: Some(Box::pin(reader)),
+            buf: BytesMut::new(),
+        })
+    }
+
+    /// Drive this body to completion, calling `f` with each chunk of data.
+    ///
+    /// This is useful for processing a body as it arrives, without
+    /// collecting it into a single buffer first. The returned future
+    /// completes once the body has ended, or with the first error returned
+    /// by either `f` or the body itself. Any remaining data is not polled
+    /// after an error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn doc() -> hyper::Result<()> {
+    /// use hyper::Body;
+    ///
+    /// let body = Body::from("hello world");
+    ///
+    /// let mut len = 0;
+    /// body.for_each(|chunk| {
+    ///     len += chunk.len();
+    ///     Ok::<_, hyper::Error>(())
+    /// })
+    /// .await?;
+    ///
+    /// assert_eq!(len, 11);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn for_each<F, E>(mut self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(Bytes) -> Result<(), E>,
+        E: From<crate::Error>,
+    {
+        while let Some(chunk) = self.data().await {
+            f(chunk?)?;
+        }
+        Ok(())
+    }
+
+    /// Split this body into two, each yielding the same chunks of data.
+    ///
+    /// This is useful to inspect a body, such as logging it, while still
+    /// forwarding it somewhere else. The original body is read as either
+    /// half is polled, and each chunk is buffered until the other half reads
+    /// it too. If one half falls more than 64KB behind the other, it yields
+    /// an error instead. Use [`Body::tee_with_max_buffered`] to configure
+    /// this limit.
+    ///
+    /// If the original body errors, both halves yield an error whose source
+    /// is that error. Trailers of the original body are not forwarded to
+    /// either half.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn doc() -> hyper::Result<()> {
+    /// use hyper::Body;
+    ///
+    /// let (logged, forwarded) = Body::from("hello world").tee();
+    ///
+    /// assert_eq!(hyper::body::to_bytes(logged).await?, "hello world");
+    /// assert_eq!(hyper::body::to_bytes(forwarded).await?, "hello world");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tee(self) -> (Body, Body) {
+        self.tee_with_max_buffered(DEFAULT_TEE_MAX_BUFFERED)
+    }
+
+    /// Split this body into two, like [`Body::tee`], allowing each half to
+    /// fall up to `max` bytes behind the other.
+    pub fn tee_with_max_buffered(self, max: usize) -> (Body, Body) {
+        let (a, b) = tee::tee(self, max);
+        (Body::new(Kind::Tee(a)), Body::new(Kind::Tee(b)))
+    }
+
     fn new(kind: Kind) -> Body {
         Body { kind, extra: None }
     }
@@ -233,10 +368,76 @@ impl Body {
             .and_then(|extra| extra.delayed_eof.take())
     }
 
-    #[cfg(any(feature = "http1", feature = "http2"))]
     fn extra_mut(&mut self) -> &mut Extra {
+        self.extra.get_or_insert_with(|| {
+            Box::new(Extra {
+                delayed_eof: None,
+                pushed_back: None,
+                #[cfg(feature = "runtime")]
+                inactivity: None,
+            })
+        })
+    }
+
+    /// Give back a chunk of data, to be returned by the next `poll_data`.
+    ///
+    /// This allows looking ahead one chunk, for instance when a parser
+    /// layered on top of a body reads more than it needs. The chunk is
+    /// included in the body's `size_hint`, and the body won't report
+    /// `is_end_stream` until it has been read again.
+    ///
+    /// # Panics
+    ///
+    /// This panics if a chunk was already pushed back and hasn't been read
+    /// since.
+    pub fn push_back(&mut self, chunk: Bytes) {
+        let pushed_back = &mut self.extra_mut().pushed_back;
+        assert!(
+            pushed_back.is_none(),
+            "Body::push_back called with a chunk already pushed back"
+        );
+        *pushed_back = Some(chunk);
+    }
+
+    /// Make the body error if no chunk is received within `duration`.
+    ///
+    /// The timer restarts each time a chunk arrives, so this guards against
+    /// a stalled peer without limiting how long a slow but steady download
+    /// may take. When it fires, `poll_data` returns an error for which
+    /// [`Error::is_timeout`](crate::Error::is_timeout) is true.
+    ///
+    /// # Cargo Feature
+    ///
+    /// Requires the `runtime` cargo feature to be enabled.
+    #[cfg(feature = "runtime")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "runtime")))]
+    pub fn with_inactivity_timeout(mut self, duration: Duration) -> Body {
+        self.extra_mut().inactivity = Some(Inactivity {
+            duration,
+            sleep: None,
+            timed_out: false,
+        });
+        self
+    }
+
+    #[cfg(feature = "runtime")]
+    fn inactivity_timed_out(&self) -> bool {
+        matches!(
+            self.extra.as_ref().and_then(|e| e.inactivity.as_ref()),
+            Some(inactivity) if inactivity.timed_out
+        )
+    }
+
+    fn pushed_back(&self) -> Option<&Bytes> {
+        self.extra
+            .as_ref()
+            .and_then(|extra| extra.pushed_back.as_ref())
+    }
+
+    fn take_pushed_back(&mut self) -> Option<Bytes> {
         self.extra
-            .get_or_insert_with(|| Box::new(Extra { delayed_eof: None }))
+            .as_mut()
+            .and_then(|extra| extra.pushed_back.take())
     }
 
     fn poll_eof(&mut self, cx: &mut task::Context<'_>) -> Poll<Option<crate::Result<Bytes>>> {
@@ -330,6 +531,8 @@ impl Body {
             #[cfg(feature = "ffi")]
             Kind::Ffi(ref mut body) => body.poll_data(cx),
 
+            Kind::Tee(ref mut half) => half.poll_data(cx),
+
             #[cfg(feature = "stream")]
             Kind::Wrapped(ref mut s) => match ready!(s.get_mut().as_mut().poll_next(cx)) {
                 Some(res) => Poll::Ready(Some(res.map_err(crate::Error::new_body))),
@@ -339,12 +542,83 @@ impl Body {
     }
 
     #[cfg(feature = "http1")]
-    pub(super) fn take_full_data(&mut self) -> Option<Bytes> {
-        if let Kind::Once(ref mut chunk) = self.kind {
-            chunk.take()
-        } else {
-            None
+    pub(super) fn take_full_data(
+        &mut self,
+        #[cfg_attr(not(feature = "stream"), allow(unused))] cx: &mut task::Context<'_>,
+    ) -> Option<Bytes> {
+        if self.pushed_back().is_some() {
+            return None;
+        }
+        match self.kind {
+            Kind::Once(ref mut chunk) => chunk.take(),
+            // A stream promising exactly one more item is looked ahead
+            // into, so that a single chunk can be sent with a known length.
+            #[cfg(feature = "stream")]
+            Kind::Wrapped(ref mut s) => {
+                let s = s.get_mut();
+                if s.size_hint() != (1, Some(1)) {
+                    return None;
+                }
+                let chunk = match s.as_mut().poll_next(cx) {
+                    Poll::Ready(Some(Ok(chunk))) => chunk,
+                    Poll::Ready(Some(Err(err))) => {
+                        self.push_front(Err(err));
+                        return None;
+                    }
+                    Poll::Ready(None) => return Some(Bytes::new()),
+                    Poll::Pending => return None,
+                };
+                // The hint may be wrong, so only trust an actual end.
+                match s.as_mut().poll_next(cx) {
+                    Poll::Ready(None) => Some(chunk),
+                    Poll::Ready(Some(next)) => {
+                        self.push_front(next);
+                        self.push_front(Ok(chunk));
+                        None
+                    }
+                    Poll::Pending => {
+                        self.push_front(Ok(chunk));
+                        None
+                    }
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Puts an item that was looked ahead at back in front of a wrapped stream.
+    #[cfg(all(feature = "http1", feature = "stream"))]
+    fn push_front(&mut self, item: Result<Bytes, Box<dyn StdError + Send + Sync>>) {
+        if let Kind::Wrapped(s) = std::mem::replace(&mut self.kind, Kind::Once(None)) {
+            let first = futures_util::stream::once(futures_util::future::ready(item));
+            let stream = futures_util::StreamExt::chain(first, s.into_inner());
+            self.kind = Kind::Wrapped(SyncWrapper::new(Box::pin(stream)));
+        }
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl Inactivity {
+    fn check(
+        &mut self,
+        res: Poll<Option<crate::Result<Bytes>>>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<crate::Result<Bytes>>> {
+        if res.is_ready() {
+            if let Some(ref mut sleep) = self.sleep {
+                sleep.as_mut().reset(Instant::now() + self.duration);
+            }
+            return res;
         }
+
+        let duration = self.duration;
+        let sleep = self
+            .sleep
+            .get_or_insert_with(|| Box::pin(tokio::time::sleep(duration)));
+        ready!(sleep.as_mut().poll(cx));
+        self.sleep = None;
+        self.timed_out = true;
+        Poll::Ready(Some(Err(crate::Error::new_body(crate::error::TimedOut))))
     }
 }
 
@@ -364,7 +638,19 @@ impl HttpBody for Body {
         mut self: Pin<&mut Self>,
         cx: &mut task::Context<'_>,
     ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
-        self.poll_eof(cx)
+        if let Some(chunk) = self.take_pushed_back() {
+            return Poll::Ready(Some(Ok(chunk)));
+        }
+        #[cfg(feature = "runtime")]
+        if self.inactivity_timed_out() {
+            return Poll::Ready(None);
+        }
+        let res = self.poll_eof(cx);
+        #[cfg(feature = "runtime")]
+        if let Some(inactivity) = self.extra.as_mut().and_then(|e| e.inactivity.as_mut()) {
+            return inactivity.check(res, cx);
+        }
+        res
     }
 
     fn poll_trailers(
@@ -398,6 +684,9 @@ impl HttpBody for Body {
     }
 
     fn is_end_stream(&self) -> bool {
+        if self.pushed_back().is_some() {
+            return false;
+        }
         match self.kind {
             Kind::Once(ref val) => val.is_none(),
             Kind::Chan { content_length, .. } => content_length == DecodedLength::ZERO,
@@ -405,6 +694,7 @@ impl HttpBody for Body {
             Kind::H2 { recv: ref h2, .. } => h2.is_end_stream(),
             #[cfg(feature = "ffi")]
             Kind::Ffi(..) => false,
+            Kind::Tee(..) => false,
             #[cfg(feature = "stream")]
             Kind::Wrapped(..) => false,
         }
@@ -423,7 +713,7 @@ impl HttpBody for Body {
             }};
         }
 
-        match self.kind {
+        let hint = match self.kind {
             Kind::Once(Some(ref val)) => SizeHint::with_exact(val.len() as u64),
             Kind::Once(None) => SizeHint::with_exact(0),
             #[cfg(feature = "stream")]
@@ -433,6 +723,20 @@ impl HttpBody for Body {
             Kind::H2 { content_length, .. } => opt_len!(content_length),
             #[cfg(feature = "ffi")]
             Kind::Ffi(..) => SizeHint::default(),
+            Kind::Tee(..) => SizeHint::default(),
+        };
+
+        match self.pushed_back() {
+            Some(chunk) => {
+                let len = chunk.len() as u64;
+                let mut with_chunk = SizeHint::new();
+                with_chunk.set_lower(hint.lower().saturating_add(len));
+                if let Some(upper) = hint.upper() {
+                    with_chunk.set_upper(upper.saturating_add(len));
+                }
+                with_chunk
+            }
+            None => hint,
         }
     }
 }
@@ -457,6 +761,47 @@ impl fmt::Debug for Body {
     }
 }
 
+#[cfg(feature = "stream")]
+const READER_CHUNK_SIZE: usize = 8192;
+
+/// Adapts an `AsyncRead` into a `Stream` of chunks, for `Body::from_async_read`.
+#[cfg(feature = "stream")]
+struct ReaderStream<R> {
+    reader: Option<Pin<Box<R>>>,
+    buf: BytesMut,
+}
+
+#[cfg(feature = "stream")]
+impl<R: AsyncRead> Stream for ReaderStream<R> {
+    type Item = std::io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Option<Self::Item>> {
+        let me = &mut *self;
+        let reader = match me.reader {
+            Some(ref mut reader) => reader,
+            None => return Poll::Ready(None),
+        };
+
+        me.buf.resize(READER_CHUNK_SIZE, 0);
+        let mut read_buf = ReadBuf::new(&mut me.buf);
+        let res = ready!(reader.as_mut().poll_read(cx, &mut read_buf));
+        let n = read_buf.filled().len();
+        me.buf.truncate(n);
+
+        match res {
+            Ok(()) if n == 0 => {
+                me.reader = None;
+                Poll::Ready(None)
+            }
+            Ok(()) => Poll::Ready(Some(Ok(me.buf.split().freeze()))),
+            Err(e) => {
+                me.reader = None;
+                Poll::Ready(Some(Err(e)))
+            }
+        }
+    }
+}
+
 /// # Optional
 ///
 /// This function requires enabling the `stream` feature in your
@@ -545,6 +890,10 @@ impl From<Cow<'static, str>> for Body {
 
 impl Sender {
     /// Check to see if this `Sender` can send more data.
+    ///
+    /// The channel buffers a single chunk, so this is `Pending` while a
+    /// chunk that was sent hasn't been received by the `Body` yet. This can
+    /// be used to apply backpressure to a producer.
     pub fn poll_ready(&mut self, cx: &mut task::Context<'_>) -> Poll<crate::Result<()>> {
         // Check if the receiver end has tried polling for the body yet
         ready!(self.poll_want(cx)?);
@@ -614,6 +963,13 @@ impl Sender {
     pub(crate) fn send_error(&mut self, err: crate::Error) {
         let _ = self.data_tx.try_send(Err(err));
     }
+
+    #[cfg(feature = "http1")]
+    pub(crate) fn send_trailers_now(&mut self, trailers: HeaderMap) {
+        if let Some(tx) = self.trailers_tx.take() {
+            let _ = tx.send(trailers);
+        }
+    }
 }
 
 impl fmt::Debug for Sender {
@@ -635,9 +991,12 @@ impl fmt::Debug for Sender {
 
 #[cfg(test)]
 mod tests {
+    use std::error::Error as StdError;
     use std::mem;
     use std::task::Poll;
 
+    use bytes::Bytes;
+
     use super::{Body, DecodedLength, HttpBody, Sender, SizeHint};
 
     #[test]
@@ -690,6 +1049,24 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "stream")]
+    #[tokio::test]
+    async fn from_async_read() {
+        let input = (0..3000).map(|i| i as u8).collect::<Vec<_>>();
+        let body = Body::from_async_read(std::io::Cursor::new(input.clone()));
+        assert_eq!(body.size_hint().exact(), None);
+
+        let bytes = crate::body::to_bytes(body).await.unwrap();
+        assert_eq!(bytes, input);
+    }
+
+    #[test]
+    fn empty_is_end_stream() {
+        let body = Body::empty();
+        assert!(body.is_end_stream());
+        assert_eq!(body.size_hint().exact(), Some(0));
+    }
+
     #[tokio::test]
     async fn channel_abort() {
         let (tx, mut rx) = Body::channel();
@@ -726,6 +1103,186 @@ mod tests {
         assert_eq!(chunk2, "chunk 2");
     }
 
+    #[tokio::test]
+    async fn for_each() {
+        let (mut tx, body) = Body::channel();
+        tokio::spawn(async move {
+            for chunk in &["one", "two", "three"] {
+                tx.send_data(Bytes::from_static(chunk.as_bytes()))
+                    .await
+                    .expect("send_data");
+            }
+        });
+
+        let mut len = 0;
+        body.for_each(|chunk| {
+            len += chunk.len();
+            Ok::<_, crate::Error>(())
+        })
+        .await
+        .expect("for_each");
+        assert_eq!(len, 11);
+    }
+
+    #[tokio::test]
+    async fn for_each_stops_on_error() {
+        let (mut tx, body) = Body::channel();
+        tokio::spawn(async move {
+            for chunk in &["one", "two", "three"] {
+                if tx
+                    .send_data(Bytes::from_static(chunk.as_bytes()))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        let mut calls = 0;
+        let err = body
+            .for_each(|chunk| {
+                calls += 1;
+                if chunk == "two" {
+                    return Err(Box::<dyn StdError + Send + Sync>::from("stop"));
+                }
+                Ok(())
+            })
+            .await
+            .expect_err("for_each");
+        assert_eq!(err.to_string(), "stop");
+        assert_eq!(calls, 2);
+    }
+
+    #[tokio::test]
+    async fn push_back() {
+        let mut body = Body::from("hello");
+        let chunk = body.data().await.unwrap().unwrap();
+        assert!(body.is_end_stream());
+
+        body.push_back(chunk);
+        assert!(!body.is_end_stream());
+        assert_eq!(body.size_hint().exact(), Some(5));
+
+        assert_eq!(body.data().await.unwrap().unwrap(), "hello");
+        assert!(body.is_end_stream());
+        assert!(body.data().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn push_back_keeps_content_length() {
+        let (mut tx, mut body) =
+            Body::new_channel(DecodedLength::new(10), /*wanter = */ false);
+        tx.try_send_data("hello".into()).expect("send");
+
+        let chunk = body.data().await.unwrap().unwrap();
+        assert_eq!(body.size_hint().exact(), Some(5));
+
+        body.push_back(chunk);
+        assert_eq!(body.size_hint().exact(), Some(10));
+        assert_eq!(body.data().await.unwrap().unwrap(), "hello");
+        assert_eq!(body.size_hint().exact(), Some(5));
+    }
+
+    #[tokio::test]
+    async fn tee() {
+        let (mut tx, body) = Body::channel();
+        tokio::spawn(async move {
+            for chunk in &["one", "two", "three"] {
+                tx.send_data(Bytes::from_static(chunk.as_bytes()))
+                    .await
+                    .expect("send_data");
+            }
+        });
+
+        async fn chunks(mut body: Body) -> Vec<Bytes> {
+            let mut chunks = Vec::new();
+            while let Some(chunk) = body.data().await {
+                chunks.push(chunk.expect("data"));
+            }
+            chunks
+        }
+
+        let (a, b) = body.tee();
+        let (a, b) = tokio::join!(chunks(a), chunks(b));
+        assert_eq!(a, ["one", "two", "three"]);
+        assert_eq!(a, b);
+    }
+
+    #[tokio::test]
+    async fn tee_max_buffered() {
+        let (mut tx, body) = Body::channel();
+        tokio::spawn(async move {
+            for chunk in &["one", "two", "three"] {
+                tx.send_data(Bytes::from_static(chunk.as_bytes()))
+                    .await
+                    .expect("send_data");
+            }
+        });
+
+        let (mut fast, mut slow) = body.tee_with_max_buffered(6);
+        assert_eq!(fast.data().await.unwrap().unwrap(), "one");
+        assert_eq!(fast.data().await.unwrap().unwrap(), "two");
+        assert_eq!(fast.data().await.unwrap().unwrap(), "three");
+        assert!(fast.data().await.is_none());
+
+        // 11 bytes were read while `slow` wasn't polled
+        slow.data()
+            .await
+            .unwrap()
+            .expect_err("slow half should error");
+        assert!(slow.data().await.is_none());
+    }
+
+    #[cfg(feature = "runtime")]
+    #[tokio::test]
+    async fn tee_shares_error() {
+        use std::time::Duration;
+
+        tokio::time::pause();
+
+        let (_tx, body) = Body::channel();
+        let body = body.with_inactivity_timeout(Duration::from_millis(100));
+
+        let (mut a, mut b) = body.tee();
+        let err = a.data().await.unwrap().expect_err("a should error");
+        assert!(err.is_timeout(), "{:?}", err);
+        let err = b.data().await.unwrap().expect_err("b should error");
+        assert!(err.is_timeout(), "{:?}", err);
+        assert!(b.data().await.is_none());
+    }
+
+    #[cfg(feature = "runtime")]
+    #[tokio::test]
+    async fn inactivity_timeout() {
+        use std::time::Duration;
+
+        tokio::time::pause();
+
+        let (mut tx, body) = Body::channel();
+        let mut body = body.with_inactivity_timeout(Duration::from_millis(100));
+        tokio::spawn(async move {
+            for chunk in &["one", "two", "three"] {
+                tx.send_data(Bytes::from_static(chunk.as_bytes()))
+                    .await
+                    .expect("send_data");
+                tokio::time::sleep(Duration::from_millis(60)).await;
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        });
+
+        // Each chunk arrives before the timeout, which restarts every time.
+        assert_eq!(body.data().await.unwrap().unwrap(), "one");
+        assert_eq!(body.data().await.unwrap().unwrap(), "two");
+        assert_eq!(body.data().await.unwrap().unwrap(), "three");
+
+        let err = body.data().await.unwrap().expect_err("inactivity timeout");
+        assert!(err.is_timeout());
+
+        // The body ends after the timeout error.
+        assert!(body.data().await.is_none());
+    }
+
     #[tokio::test]
     async fn channel_empty() {
         let (_, mut rx) = Body::channel();
@@ -763,6 +1320,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn channel_ready_after_drain() {
+        let (mut tx, mut rx) = Body::new_channel(DecodedLength::CHUNKED, /*wanter = */ false);
+
+        tx.try_send_data("chunk 1".into()).expect("send 1");
+
+        {
+            let mut tx_ready = tokio_test::task::spawn(tx.ready());
+            assert!(tx_ready.poll().is_pending(), "tx isn't ready while full");
+
+            let mut rx_data = tokio_test::task::spawn(rx.data());
+            match rx_data.poll() {
+                Poll::Ready(Some(Ok(chunk))) => assert_eq!(chunk, "chunk 1"),
+                unexpected => panic!("rx poll data unexpected: {:?}", unexpected),
+            }
+            drop(rx_data);
+
+            assert!(tx_ready.is_woken(), "draining rx wakes tx");
+            assert!(tx_ready.poll().is_ready(), "tx is ready after a drain");
+        }
+
+        tx.try_send_data("chunk 2".into()).expect("send 2");
+    }
+
     #[test]
     fn channel_notices_closure() {
         let (mut tx, rx) = Body::new_channel(DecodedLength::CHUNKED, /*wanter = */ true);
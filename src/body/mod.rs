@@ -22,18 +22,22 @@ pub use http_body::SizeHint;
 pub use self::aggregate::aggregate;
 pub use self::body::{Body, Sender};
 pub(crate) use self::length::DecodedLength;
-pub use self::to_bytes::to_bytes;
+pub use self::to_bytes::{to_bytes, to_bytes_limited, to_vec};
 
 mod aggregate;
 mod body;
 mod length;
+mod tee;
 mod to_bytes;
 
 /// An optimization to try to take a full body if immediately available.
 ///
 /// This is currently limited to *only* `hyper::Body`s.
 #[cfg(feature = "http1")]
-pub(crate) fn take_full_data<T: HttpBody + 'static>(body: &mut T) -> Option<T::Data> {
+pub(crate) fn take_full_data<T: HttpBody + 'static>(
+    body: &mut T,
+    cx: &mut crate::common::task::Context<'_>,
+) -> Option<T::Data> {
     use std::any::{Any, TypeId};
 
     // This static type check can be optimized at compile-time.
@@ -41,7 +45,7 @@ pub(crate) fn take_full_data<T: HttpBody + 'static>(body: &mut T) -> Option<T::D
         let mut full = (body as &mut dyn Any)
             .downcast_mut::<Body>()
             .expect("must be Body")
-            .take_full_data();
+            .take_full_data(cx);
         // This second cast is required to make the type system happy.
         // Without it, the compiler cannot reason that the type is actually
         // `T::Data`. Oh wells.
@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::task::Waker;
+
+use bytes::Bytes;
+use http_body::Body as HttpBody;
+
+use super::Body;
+use crate::common::{task, Pin, Poll};
+
+/// One half of a `Body` split with `Body::tee`.
+///
+/// Whichever half is polled first reads from the original body, and queues
+/// a copy of each chunk for the other half.
+pub(super) struct TeeHalf {
+    shared: Arc<Mutex<Shared>>,
+    idx: usize,
+}
+
+struct Shared {
+    body: Body,
+    halves: [Half; 2],
+    max_buffered: usize,
+    /// Set once the original body has ended, with an error or not.
+    eof: bool,
+}
+
+struct Half {
+    queue: VecDeque<Bytes>,
+    buffered: usize,
+    state: State,
+    waker: Option<Waker>,
+}
+
+enum State {
+    Open,
+    /// The original body errored while the other half was polling it.
+    Errored(Arc<crate::Error>),
+    /// This half fell more than `max_buffered` bytes behind.
+    Overflowed,
+    /// Either dropped, or finished after yielding an error.
+    Closed,
+}
+
+#[derive(Debug)]
+struct Overflowed;
+
+/// An error of the original body, yielded by both halves.
+#[derive(Debug)]
+struct SharedError(Arc<crate::Error>);
+
+pub(super) fn tee(body: Body, max_buffered: usize) -> (TeeHalf, TeeHalf) {
+    let shared = Arc::new(Mutex::new(Shared {
+        body,
+        halves: [Half::new(), Half::new()],
+        max_buffered,
+        eof: false,
+    }));
+    (
+        TeeHalf {
+            shared: shared.clone(),
+            idx: 0,
+        },
+        TeeHalf { shared, idx: 1 },
+    )
+}
+
+impl Half {
+    fn new() -> Half {
+        Half {
+            queue: VecDeque::new(),
+            buffered: 0,
+            state: State::Open,
+            waker: None,
+        }
+    }
+}
+
+impl TeeHalf {
+    pub(super) fn poll_data(
+        &mut self,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<crate::Result<Bytes>>> {
+        let mut shared = self.shared.lock().unwrap();
+        let shared = &mut *shared;
+        let [first, second] = &mut shared.halves;
+        let (me, other) = if self.idx == 0 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        if let Some(chunk) = me.queue.pop_front() {
+            me.buffered -= chunk.len();
+            return Poll::Ready(Some(Ok(chunk)));
+        }
+
+        match std::mem::replace(&mut me.state, State::Closed) {
+            State::Open => me.state = State::Open,
+            State::Errored(err) => {
+                return Poll::Ready(Some(Err(crate::Error::new_body(SharedError(err)))))
+            }
+            State::Overflowed => return Poll::Ready(Some(Err(crate::Error::new_body(Overflowed)))),
+            State::Closed => return Poll::Ready(None),
+        }
+
+        if shared.eof {
+            return Poll::Ready(None);
+        }
+
+        let res = match Pin::new(&mut shared.body).poll_data(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => {
+                me.waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+        };
+
+        if let Some(waker) = other.waker.take() {
+            waker.wake();
+        }
+        match res {
+            Some(Ok(ref chunk)) => {
+                if let State::Open = other.state {
+                    other.buffered += chunk.len();
+                    if other.buffered > shared.max_buffered {
+                        other.queue.clear();
+                        other.buffered = 0;
+                        other.state = State::Overflowed;
+                    } else {
+                        other.queue.push_back(chunk.clone());
+                    }
+                }
+            }
+            Some(Err(err)) => {
+                shared.eof = true;
+                if let State::Open = other.state {
+                    // `crate::Error` can't be cloned, so both halves yield
+                    // an error with the original as its source.
+                    let err = Arc::new(err);
+                    other.state = State::Errored(err.clone());
+                    return Poll::Ready(Some(Err(crate::Error::new_body(SharedError(err)))));
+                }
+                return Poll::Ready(Some(Err(err)));
+            }
+            None => shared.eof = true,
+        }
+        Poll::Ready(res)
+    }
+}
+
+impl Drop for TeeHalf {
+    fn drop(&mut self) {
+        // The other half may be waiting on a chunk this half was reading,
+        // so let it poll the original body itself.
+        let mut shared = match self.shared.lock() {
+            Ok(shared) => shared,
+            Err(_) => return,
+        };
+        let halves = &mut shared.halves;
+        halves[self.idx] = Half {
+            state: State::Closed,
+            ..Half::new()
+        };
+        if let Some(waker) = halves[1 - self.idx].waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl fmt::Display for Overflowed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("body tee fell too far behind")
+    }
+}
+
+impl StdError for Overflowed {}
+
+impl fmt::Display for SharedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("original body errored")
+    }
+}
+
+impl StdError for SharedError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&*self.0)
+    }
+}
@@ -12,7 +12,9 @@ use super::HttpBody;
 ///
 /// Care needs to be taken if the remote is untrusted. The function doesn't implement any length
 /// checks and an malicious peer might make it consume arbitrary amounts of memory. Checking the
-/// `Content-Length` is a possibility, but it is not strictly mandated to be present.
+/// `Content-Length` is a possibility, but it is not strictly mandated to be present. The
+/// [`to_bytes_limited`](crate::body::to_bytes_limited()) function can be used to cap the
+/// amount of data buffered.
 ///
 /// # Example
 ///
@@ -75,3 +77,124 @@ where
 
     Ok(vec.into())
 }
+
+/// Concatenate the buffers from a body into a `Vec<u8>` asynchronously.
+///
+/// Each buffer is appended to the returned vector as it arrives, so there
+/// is no further conversion once the body has ended. The same note about
+/// untrusted peers as for [`to_bytes`](crate::body::to_bytes()) applies.
+///
+/// # Example
+///
+/// ```
+/// # async fn doc() -> hyper::Result<()> {
+/// let body = hyper::Body::from("hello world");
+///
+/// let vec = hyper::body::to_vec(body).await?;
+/// assert_eq!(vec, b"hello world");
+/// # Ok(())
+/// # }
+/// ```
+pub async fn to_vec<T>(body: T) -> Result<Vec<u8>, T::Error>
+where
+    T: HttpBody,
+{
+    futures_util::pin_mut!(body);
+
+    let mut vec = Vec::new();
+    while let Some(buf) = body.data().await {
+        vec.put(buf?);
+    }
+
+    Ok(vec)
+}
+
+/// Concatenate the buffers from a body into a single `Bytes`, stopping with
+/// an error once more than `limit` bytes have been received.
+///
+/// If the body's size hint already exceeds `limit`, this returns an error
+/// without reading any data. Otherwise the body is buffered until it ends,
+/// or until its accumulated length passes `limit`. Hitting the limit yields
+/// an error for which [`Error::is_body_too_large`](crate::Error::is_body_too_large)
+/// returns `true`.
+///
+/// # Example
+///
+/// ```
+/// # async fn doc() {
+/// let body = hyper::Body::from("hello world");
+///
+/// let err = hyper::body::to_bytes_limited(body, 5).await.unwrap_err();
+/// assert!(err.is_body_too_large());
+/// # }
+/// ```
+pub async fn to_bytes_limited<T>(body: T, limit: usize) -> crate::Result<Bytes>
+where
+    T: HttpBody<Error = crate::Error>,
+{
+    futures_util::pin_mut!(body);
+
+    if body.size_hint().lower() > limit as u64 {
+        return Err(crate::Error::new_body_too_large());
+    }
+
+    let mut vec = Vec::new();
+    while let Some(buf) = body.data().await {
+        let buf = buf?;
+        if buf.remaining() > limit - vec.len() {
+            return Err(crate::Error::new_body_too_large());
+        }
+        vec.put(buf);
+    }
+
+    Ok(vec.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_bytes_limited, to_vec};
+    use crate::Body;
+
+    #[tokio::test]
+    async fn to_vec_multiple_chunks() {
+        let (mut tx, body) = Body::channel();
+        tokio::spawn(async move {
+            tx.send_data("hello".into()).await.unwrap();
+            tx.send_data(" ".into()).await.unwrap();
+            tx.send_data("world".into()).await.unwrap();
+        });
+
+        let vec = to_vec(body).await.unwrap();
+        assert_eq!(vec, b"hello world".to_vec());
+    }
+
+    #[tokio::test]
+    async fn limited_under_limit() {
+        let (mut tx, body) = Body::channel();
+        tokio::spawn(async move {
+            tx.send_data("hello ".into()).await.unwrap();
+            tx.send_data("world".into()).await.unwrap();
+        });
+
+        let bytes = to_bytes_limited(body, 11).await.unwrap();
+        assert_eq!(bytes, "hello world");
+    }
+
+    #[tokio::test]
+    async fn limited_over_limit() {
+        let (mut tx, body) = Body::channel();
+        tokio::spawn(async move {
+            tx.send_data("hello ".into()).await.unwrap();
+            let _ = tx.send_data("world".into()).await;
+        });
+
+        let err = to_bytes_limited(body, 10).await.unwrap_err();
+        assert!(err.is_body_too_large());
+
+        // a known length is rejected before reading
+        let err = to_bytes_limited(Body::from("hello world"), 10)
+            .await
+            .unwrap_err();
+        assert!(err.is_body_too_large());
+    }
+}
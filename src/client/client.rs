@@ -19,6 +19,7 @@ use super::pool::{
 use super::HttpConnector;
 use crate::body::{Body, HttpBody};
 use crate::common::{exec::BoxSendFuture, sync_wrapper::SyncWrapper, lazy as hyper_lazy, task, Future, Lazy, Pin, Poll};
+use crate::ext::ProxyMode;
 use crate::rt::Executor;
 
 /// A Client to make outgoing HTTP requests.
@@ -140,6 +141,28 @@ where
         self.request(req)
     }
 
+    /// Send a `POST` request with the supplied body to the supplied `Uri`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature  = "runtime")]
+    /// # fn run () {
+    /// use hyper::{Body, Client, Uri};
+    ///
+    /// let client = Client::new();
+    ///
+    /// let future = client.post(Uri::from_static("http://httpbin.org/post"), Body::from("Hallo!"));
+    /// # }
+    /// # fn main() {}
+    /// ```
+    pub fn post(&self, uri: Uri, body: B) -> ResponseFuture {
+        let mut req = Request::new(body);
+        *req.method_mut() = Method::POST;
+        *req.uri_mut() = uri;
+        self.request(req)
+    }
+
     /// Send a constructed `Request` using this `Client`.
     ///
     /// # Example
@@ -188,6 +211,16 @@ where
         ResponseFuture::new(self.clone().retryably_send_request(req, pool_key))
     }
 
+    /// Close all idle connections held in the connection pool.
+    ///
+    /// This can be useful when something the existing connections depend
+    /// on has changed, such as rotated TLS certificates or updated DNS
+    /// records. Connections currently in use are not affected, and may be
+    /// returned to the pool once their requests complete.
+    pub fn clear_idle_connections(&self) {
+        self.pool.clear_idle();
+    }
+
     async fn retryably_send_request(
         self,
         mut req: Request<B>,
@@ -204,9 +237,13 @@ where
                     mut req,
                     reason,
                 }) => {
-                    if !self.config.retry_canceled_requests || !connection_reused {
+                    if !self.config.retry_canceled_requests
+                        || !connection_reused
+                        || !req.method().is_idempotent()
+                    {
                         // if client disabled, don't retry
                         // a fresh connection means we definitely can't retry
+                        // a non-idempotent method is never retried
                         return Err(reason);
                     }
 
@@ -263,7 +300,7 @@ where
             // CONNECT always sends authority-form, so check it first...
             if req.method() == Method::CONNECT {
                 authority_form(req.uri_mut());
-            } else if pooled.conn_info.is_proxied {
+            } else if pooled.conn_info.is_proxied || req.extensions().get::<ProxyMode>().is_some() {
                 absolute_form(req.uri_mut());
             } else {
                 origin_form(req.uri_mut());
@@ -1149,6 +1186,19 @@ impl Builder {
         self
     }
 
+    /// Sets the max size of received header frames for HTTP2.
+    ///
+    /// This is advertised to the peer as `SETTINGS_MAX_HEADER_LIST_SIZE`.
+    /// APIs that send many or very large headers may need to raise it.
+    ///
+    /// Default is currently 16MB, but may change.
+    #[cfg(feature = "http2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
+    pub fn http2_max_header_list_size(&mut self, max: u32) -> &mut Self {
+        self.conn_builder.http2_max_header_list_size(max);
+        self
+    }
+
     /// Sets an interval for HTTP2 Ping frames should be sent to keep a
     /// connection alive.
     ///
@@ -1244,6 +1294,9 @@ impl Builder {
     /// connection, and then encounters an error immediately as the idle
     /// connection was found to be unusable.
     ///
+    /// Only requests with an idempotent method (see
+    /// [`Method::is_idempotent`](http::Method::is_idempotent)) are retried.
+    ///
     /// When this is set to `false`, the related `ResponseFuture` would instead
     /// resolve to an `Error::Cancel`.
     ///
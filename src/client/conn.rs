@@ -72,6 +72,8 @@ use tracing::{debug, trace};
 
 use super::dispatch;
 use crate::body::HttpBody;
+#[cfg(feature = "http2")]
+use crate::common::io::Rewind;
 #[cfg(not(all(feature = "http1", feature = "http2")))]
 use crate::common::Never;
 use crate::common::{
@@ -82,7 +84,7 @@ use crate::proto;
 use crate::rt::Executor;
 #[cfg(feature = "http1")]
 use crate::upgrade::Upgraded;
-use crate::{Body, Request, Response};
+use crate::{Body, Request, Response, Version};
 
 #[cfg(feature = "http1")]
 type Http1Dispatcher<T, B> =
@@ -130,6 +132,7 @@ where
 /// The sender side of an established connection.
 pub struct SendRequest<B> {
     dispatch: dispatch::Sender<Request<B>, Response<Body>>,
+    version: Version,
 }
 
 /// A future that processes all HTTP state for the IO object.
@@ -157,6 +160,7 @@ pub struct Builder {
     h1_title_case_headers: bool,
     h1_preserve_header_case: bool,
     h1_read_buf_exact_size: Option<usize>,
+    h1_read_once_per_poll: bool,
     h1_max_buf_size: Option<usize>,
     #[cfg(feature = "ffi")]
     h1_headers_raw: bool,
@@ -227,6 +231,13 @@ impl<B> SendRequest<B> {
         self.dispatch.poll_ready(cx)
     }
 
+    /// Returns the HTTP version spoken on the associated connection.
+    ///
+    /// HTTP/1 connections report `HTTP_11`.
+    pub fn http_version(&self) -> Version {
+        self.version
+    }
+
     pub(super) async fn when_ready(self) -> crate::Result<Self> {
         let mut me = Some(self);
         future::poll_fn(move |cx| {
@@ -478,6 +489,29 @@ where
         }
     }
 
+    /// Write any queued request bytes to the IO, and flush it.
+    ///
+    /// This doesn't read responses, so it can be used to push a request out
+    /// when driving the connection manually, without waiting for the
+    /// response. HTTP/2 connections flush as they are polled, so for them
+    /// this returns ready right away.
+    pub fn poll_flush(
+        &mut self,
+        #[cfg_attr(not(feature = "http1"), allow(unused))] cx: &mut task::Context<'_>,
+    ) -> Poll<crate::Result<()>> {
+        match *self.inner.as_mut().expect("already upgraded") {
+            #[cfg(feature = "http1")]
+            ProtoClient::H1 { ref mut h1 } => h1.poll_write_flush(cx),
+            #[cfg(feature = "http2")]
+            ProtoClient::H2 { .. } => Poll::Ready(Ok(())),
+
+            #[cfg(not(feature = "http1"))]
+            ProtoClient::H1 { ref mut h1 } => match h1.0 {},
+            #[cfg(not(feature = "http2"))]
+            ProtoClient::H2 { ref mut h2, .. } => match h2.0 {},
+        }
+    }
+
     /// Prevent shutdown of the underlying IO object at the end of service the request,
     /// instead run `into_parts`. This is a convenience wrapper over `poll_without_shutdown`.
     pub fn without_shutdown(self) -> impl Future<Output = crate::Result<Parts<T>>> {
@@ -503,9 +537,10 @@ where
             proto::Dispatched::Shutdown => Poll::Ready(Ok(())),
             #[cfg(feature = "http1")]
             proto::Dispatched::Upgrade(pending) => match self.inner.take() {
-                Some(ProtoClient::H1 { h1 }) => {
+                Some(ProtoClient::H1 { mut h1 }) => {
+                    let write_buf = h1.take_write_buf();
                     let (io, buf, _) = h1.into_inner();
-                    pending.fulfill(Upgraded::new(io, buf));
+                    pending.fulfill(Upgraded::new(io, buf).with_write_buf(write_buf));
                     Poll::Ready(Ok(()))
                 }
                 _ => {
@@ -538,6 +573,7 @@ impl Builder {
             h09_responses: false,
             h1_writev: None,
             h1_read_buf_exact_size: None,
+            h1_read_once_per_poll: false,
             h1_parser_config: Default::default(),
             h1_title_case_headers: false,
             h1_preserve_header_case: false,
@@ -655,6 +691,25 @@ impl Builder {
         self
     }
 
+    /// Set whether HTTP/1 connections should read from the transport only
+    /// once each time they are polled while parsing a response head.
+    ///
+    /// By default, the connection keeps reading until the transport would
+    /// block. With this enabled, it instead yields after every read, so a
+    /// response head split across several writes takes as many polls to
+    /// parse. This is mostly useful to make backpressure deterministic in
+    /// tests.
+    ///
+    /// This doesn't change the read buffer size, see
+    /// [`http1_read_buf_exact_size`](Builder::http1_read_buf_exact_size)
+    /// for that.
+    ///
+    /// Default is false.
+    pub fn http1_read_once_per_poll(&mut self, enabled: bool) -> &mut Builder {
+        self.h1_read_once_per_poll = enabled;
+        self
+    }
+
     /// Set the maximum buffer size for the connection.
     ///
     /// Default is ~400kb.
@@ -763,6 +818,19 @@ impl Builder {
         self
     }
 
+    /// Sets the max size of received header frames for HTTP2.
+    ///
+    /// This is advertised to the peer as `SETTINGS_MAX_HEADER_LIST_SIZE`.
+    /// APIs that send many or very large headers may need to raise it.
+    ///
+    /// Default is currently 16MB, but may change.
+    #[cfg(feature = "http2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
+    pub fn http2_max_header_list_size(&mut self, max: u32) -> &mut Self {
+        self.h2_builder.max_header_list_size = max;
+        self
+    }
+
     /// Sets an interval for HTTP2 Ping frames should be sent to keep a
     /// connection alive.
     ///
@@ -861,6 +929,27 @@ impl Builder {
         &self,
         io: T,
     ) -> impl Future<Output = crate::Result<(SendRequest<B>, Connection<T, B>)>>
+    where
+        T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        B: HttpBody + 'static,
+        B::Data: Send,
+        B::Error: Into<Box<dyn StdError + Send + Sync>>,
+    {
+        self.handshake_with_read_buf(io, Bytes::new())
+    }
+
+    /// Constructs a connection with the configured options and IO, and
+    /// bytes that were already read from the IO.
+    ///
+    /// This is useful when some bytes had to be read to decide whether to
+    /// hand the IO to hyper. They are parsed before anything else is read
+    /// from the IO. With HTTP/1, they are the start of the response to the
+    /// first request sent. This is the inverse of [`Parts::read_buf`].
+    pub fn handshake_with_read_buf<T, B>(
+        &self,
+        io: T,
+        read_buf: Bytes,
+    ) -> impl Future<Output = crate::Result<(SendRequest<B>, Connection<T, B>)>>
     where
         T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
         B: HttpBody + 'static,
@@ -873,6 +962,7 @@ impl Builder {
             trace!("client handshake {:?}", opts.version);
 
             let (tx, rx) = dispatch::channel();
+            let version;
             let proto = match opts.version {
                 #[cfg(feature = "http1")]
                 Proto::Http1 => {
@@ -901,24 +991,40 @@ impl Builder {
                     if let Some(sz) = opts.h1_read_buf_exact_size {
                         conn.set_read_buf_exact_size(sz);
                     }
+                    if opts.h1_read_once_per_poll {
+                        conn.set_read_once_per_poll();
+                    }
                     if let Some(max) = opts.h1_max_buf_size {
                         conn.set_max_buf_size(max);
                     }
+                    if !read_buf.is_empty() {
+                        conn.prefill_read_buf(&read_buf);
+                    }
                     let cd = proto::h1::dispatch::Client::new(rx);
                     let dispatch = proto::h1::Dispatcher::new(cd, conn);
+                    version = Version::HTTP_11;
                     ProtoClient::H1 { h1: dispatch }
                 }
                 #[cfg(feature = "http2")]
                 Proto::Http2 => {
-                    let h2 =
+                    let h2 = if read_buf.is_empty() {
+                        proto::h2::client::handshake(io, rx, &opts.h2_builder, opts.exec.clone())
+                            .await?
+                    } else {
+                        let io = Rewind::new_buffered(io, read_buf);
                         proto::h2::client::handshake(io, rx, &opts.h2_builder, opts.exec.clone())
-                            .await?;
+                            .await?
+                    };
+                    version = Version::HTTP_2;
                     ProtoClient::H2 { h2 }
                 }
             };
 
             Ok((
-                SendRequest { dispatch: tx },
+                SendRequest {
+                    dispatch: tx,
+                    version,
+                },
                 Connection { inner: Some(proto) },
             ))
         }
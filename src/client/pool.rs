@@ -126,6 +126,18 @@ impl<T> Pool<T> {
         self.inner.is_some()
     }
 
+    /// Drops every idle connection in the pool.
+    ///
+    /// Connections currently checked out are unaffected, and may still be
+    /// returned to the pool when they are done.
+    pub(super) fn clear_idle(&self) {
+        if let Some(ref enabled) = self.inner {
+            let mut inner = enabled.lock().unwrap();
+            trace!("clearing {} idle pool keys", inner.idle.len());
+            inner.idle.clear();
+        }
+    }
+
     #[cfg(test)]
     pub(super) fn no_timer(&self) {
         // Prevent an actual interval from being created for this pool...
@@ -910,6 +922,33 @@ mod tests {
         assert!(pool.locked().idle.get(&key).is_none());
     }
 
+    #[test]
+    fn test_pool_clear_idle() {
+        let pool = pool_no_timer();
+        let key = host_key("foo");
+
+        pool.pooled(c(key.clone()), Uniq(41));
+        pool.pooled(c(key.clone()), Uniq(5));
+        pool.pooled(c(key.clone()), Uniq(99));
+        let held = pool.pooled(c(key.clone()), Uniq(7));
+
+        assert_eq!(
+            pool.locked().idle.get(&key).map(|entries| entries.len()),
+            Some(3)
+        );
+
+        pool.clear_idle();
+        assert!(pool.locked().idle.is_empty());
+
+        // a connection still in use is untouched, and returns to the pool
+        assert_eq!(*held, Uniq(7));
+        drop(held);
+        assert_eq!(
+            pool.locked().idle.get(&key).map(|entries| entries.len()),
+            Some(1)
+        );
+    }
+
     #[test]
     fn test_pool_max_idle_per_host() {
         let pool = pool_max_idle_no_timer(2);
@@ -29,7 +29,7 @@ pub(super) enum Kind {
     UnexpectedMessage,
     /// A pending item was dropped before ever being processed.
     Canceled,
-    /// Indicates a channel (client or body sender) is closed.
+    /// Indicates a channel (client, body or push sender) is closed.
     ChannelClosed,
     /// An `io::Error` that occurred while trying to read or write to a network stream.
     #[cfg(any(feature = "http1", feature = "http2"))]
@@ -47,8 +47,9 @@ pub(super) enum Kind {
     /// User took too long to send headers
     #[cfg(all(feature = "http1", feature = "server", feature = "runtime"))]
     HeaderTimeout,
+    /// An incoming body was larger than the configured limit.
+    BodyTooLarge,
     /// Error while reading a body from connection.
-    #[cfg(any(feature = "http1", feature = "http2", feature = "stream"))]
     Body,
     /// Error while writing a body to connection.
     #[cfg(any(feature = "http1", feature = "http2"))]
@@ -73,6 +74,10 @@ pub(super) enum Parse {
     Uri,
     #[cfg_attr(not(all(feature = "http1", feature = "server")), allow(unused))]
     UriTooLong,
+    #[cfg(all(feature = "http1", feature = "server"))]
+    MethodNotAllowed,
+    #[cfg(all(feature = "http1", feature = "server"))]
+    ProxyHeader,
     Header(Header),
     TooLarge,
     Status,
@@ -89,6 +94,10 @@ pub(super) enum Header {
     TransferEncodingInvalid,
     #[cfg(feature = "http1")]
     TransferEncodingUnexpected,
+    #[cfg(all(feature = "http1", feature = "server"))]
+    HostMissing,
+    #[cfg(all(feature = "http1", feature = "server"))]
+    ExpectUnsupported,
 }
 
 #[derive(Debug)]
@@ -137,6 +146,9 @@ pub(super) enum User {
     #[cfg(feature = "server")]
     WithoutShutdownNonHttp1,
 
+    /// User pushed more HTTP/2 responses than can be queued.
+    TooManyPushes,
+
     /// User aborted in an FFI callback.
     #[cfg(feature = "ffi")]
     AbortedByCallback,
@@ -196,6 +208,11 @@ impl Error {
         matches!(self.inner.kind, Kind::BodyWriteAborted)
     }
 
+    /// Returns true if an incoming body was larger than the configured limit.
+    pub fn is_body_too_large(&self) -> bool {
+        matches!(self.inner.kind, Kind::BodyTooLarge)
+    }
+
     /// Returns true if the error was caused by a timeout.
     pub fn is_timeout(&self) -> bool {
         self.find_source::<TimedOut>().is_some()
@@ -258,6 +275,10 @@ impl Error {
         Error::new(Kind::Parse(Parse::TooLarge))
     }
 
+    pub(super) fn new_body_too_large() -> Error {
+        Error::new(Kind::BodyTooLarge)
+    }
+
     #[cfg(feature = "http1")]
     pub(super) fn new_version_h2() -> Error {
         Error::new(Kind::Parse(Parse::VersionH2))
@@ -294,7 +315,6 @@ impl Error {
         Error::new(Kind::ChannelClosed)
     }
 
-    #[cfg(any(feature = "http1", feature = "http2", feature = "stream"))]
     pub(super) fn new_body<E: Into<Cause>>(cause: E) -> Error {
         Error::new(Kind::Body).with(cause)
     }
@@ -382,6 +402,10 @@ impl Error {
         Error::new(Kind::Shutdown).with(cause)
     }
 
+    pub(super) fn new_user_too_many_pushes() -> Error {
+        Error::new_user(User::TooManyPushes)
+    }
+
     #[cfg(feature = "ffi")]
     pub(super) fn new_user_aborted_by_callback() -> Error {
         Error::new_user(User::AbortedByCallback)
@@ -404,6 +428,10 @@ impl Error {
             Kind::Parse(Parse::VersionH2) => "invalid HTTP version parsed (found HTTP2 preface)",
             Kind::Parse(Parse::Uri) => "invalid URI",
             Kind::Parse(Parse::UriTooLong) => "URI too long",
+            #[cfg(all(feature = "http1", feature = "server"))]
+            Kind::Parse(Parse::MethodNotAllowed) => "method not allowed",
+            #[cfg(all(feature = "http1", feature = "server"))]
+            Kind::Parse(Parse::ProxyHeader) => "invalid PROXY protocol header parsed",
             Kind::Parse(Parse::Header(Header::Token)) => "invalid HTTP header parsed",
             #[cfg(feature = "http1")]
             Kind::Parse(Parse::Header(Header::ContentLengthInvalid)) => {
@@ -417,6 +445,10 @@ impl Error {
             Kind::Parse(Parse::Header(Header::TransferEncodingUnexpected)) => {
                 "unexpected transfer-encoding parsed"
             }
+            #[cfg(all(feature = "http1", feature = "server"))]
+            Kind::Parse(Parse::Header(Header::HostMissing)) => "missing host header",
+            #[cfg(all(feature = "http1", feature = "server"))]
+            Kind::Parse(Parse::Header(Header::ExpectUnsupported)) => "unsupported expect header",
             Kind::Parse(Parse::TooLarge) => "message head is too large",
             Kind::Parse(Parse::Status) => "invalid HTTP status-code parsed",
             Kind::Parse(Parse::Internal) => {
@@ -435,7 +467,7 @@ impl Error {
             Kind::Accept => "error accepting connection",
             #[cfg(all(feature = "http1", feature = "server", feature = "runtime"))]
             Kind::HeaderTimeout => "read header from client timeout",
-            #[cfg(any(feature = "http1", feature = "http2", feature = "stream"))]
+            Kind::BodyTooLarge => "incoming body is larger than the configured limit",
             Kind::Body => "error reading a body from connection",
             #[cfg(any(feature = "http1", feature = "http2"))]
             Kind::BodyWrite => "error writing a body to connection",
@@ -478,6 +510,7 @@ impl Error {
             Kind::User(User::WithoutShutdownNonHttp1) => {
                 "without_shutdown() called on a non-HTTP/1 connection"
             }
+            Kind::User(User::TooManyPushes) => "too many pushes queued",
             #[cfg(feature = "ffi")]
             Kind::User(User::AbortedByCallback) => "operation aborted by an application callback",
         }
@@ -535,6 +568,16 @@ impl Parse {
     pub(crate) fn transfer_encoding_unexpected() -> Self {
         Parse::Header(Header::TransferEncodingUnexpected)
     }
+
+    #[cfg(all(feature = "http1", feature = "server"))]
+    pub(crate) fn host_missing() -> Self {
+        Parse::Header(Header::HostMissing)
+    }
+
+    #[cfg(all(feature = "http1", feature = "server"))]
+    pub(crate) fn expect_unsupported() -> Self {
+        Parse::Header(Header::ExpectUnsupported)
+    }
 }
 
 impl From<httparse::Error> for Parse {
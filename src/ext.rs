@@ -1,9 +1,13 @@
 //! HTTP extensions
 
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::task::Waker;
+
 use bytes::Bytes;
 #[cfg(feature = "http1")]
 use http::header::{HeaderName, IntoHeaderName, ValueIter};
-use http::HeaderMap;
+use http::{HeaderMap, Request, Response};
 
 /// A map from header names to their original casing as received in an HTTP message.
 ///
@@ -62,3 +66,273 @@ impl HeaderCaseMap {
         self.0.append(name, orig);
     }
 }
+
+/// A reason phrase in an HTTP/1 response.
+///
+/// When an HTTP/1 client receives a response whose reason phrase differs
+/// from the canonical reason of its status code, such as
+/// `HTTP/1.1 200 Totally Fine`, the original phrase is kept in the
+/// response extensions:
+///
+/// ```no_run
+/// use hyper::ext::ReasonPhrase;
+/// use hyper::{Body, Response};
+///
+/// fn reason(res: &Response<Body>) -> Option<&str> {
+///     res.extensions()
+///         .get::<ReasonPhrase>()
+///         .and_then(|reason| reason.to_str())
+/// }
+/// ```
+///
+/// Responses with the canonical reason phrase do not carry this extension,
+/// and their reason can be found with `StatusCode::canonical_reason`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReasonPhrase(Bytes);
+
+impl ReasonPhrase {
+    /// Gets the reason phrase as bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Gets the reason phrase as a `&str`, if it is valid UTF-8.
+    pub fn to_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.0).ok()
+    }
+
+    #[cfg(all(feature = "client", feature = "http1"))]
+    pub(crate) fn from_bytes_unchecked(reason: Bytes) -> Self {
+        ReasonPhrase(reason)
+    }
+}
+
+impl AsRef<[u8]> for ReasonPhrase {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// The HTTP/2 stream identifier of a request received by a server.
+///
+/// Servers insert this into the extensions of every HTTP/2 request, so
+/// services can correlate logs with the stream a request arrived on:
+///
+/// ```no_run
+/// use hyper::ext::Http2StreamId;
+/// use hyper::{Body, Request, Response};
+/// use std::convert::Infallible;
+///
+/// async fn handle(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+///     if let Some(id) = req.extensions().get::<Http2StreamId>() {
+///         println!("request on stream {}", id.as_u32());
+///     }
+///     Ok(Response::new(Body::empty()))
+/// }
+/// ```
+///
+/// HTTP/1 requests do not carry this extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Http2StreamId(u32);
+
+impl Http2StreamId {
+    #[cfg(all(feature = "http2", feature = "server"))]
+    pub(crate) fn new(id: u32) -> Self {
+        Http2StreamId(id)
+    }
+
+    /// Returns the stream identifier as a `u32`.
+    pub fn as_u32(&self) -> u32 {
+        self.0
+    }
+}
+
+/// The addresses announced by a PROXY protocol header.
+///
+/// If a server connection was configured with [`accept_proxy_protocol`],
+/// the addresses from the PROXY v1 line that precedes the HTTP/1 traffic
+/// are inserted into the extensions of every request on that connection:
+///
+/// ```no_run
+/// use hyper::ext::ProxyAddrs;
+/// use hyper::{Body, Request, Response};
+/// use std::convert::Infallible;
+///
+/// async fn handle(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+///     if let Some(addrs) = req.extensions().get::<ProxyAddrs>() {
+///         println!("request from {}", addrs.source());
+///     }
+///     Ok(Response::new(Body::empty()))
+/// }
+/// ```
+///
+/// Connections whose header announced `UNKNOWN` do not carry this extension.
+///
+/// [`accept_proxy_protocol`]: crate::server::conn::Http::accept_proxy_protocol
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ProxyAddrs {
+    source: SocketAddr,
+    destination: SocketAddr,
+}
+
+impl ProxyAddrs {
+    #[cfg(all(feature = "http1", feature = "server"))]
+    pub(crate) fn new(source: SocketAddr, destination: SocketAddr) -> Self {
+        ProxyAddrs {
+            source,
+            destination,
+        }
+    }
+
+    /// Returns the address of the client that connected to the proxy.
+    pub fn source(&self) -> SocketAddr {
+        self.source
+    }
+
+    /// Returns the address the client connected to on the proxy.
+    pub fn destination(&self) -> SocketAddr {
+        self.destination
+    }
+}
+
+/// Marks a client request to be sent as if to a forward proxy.
+///
+/// An HTTP/1 [`Client`] normally sends the request-target in origin-form,
+/// like `GET /path HTTP/1.1`, unless its connector reported a proxied
+/// connection. Inserting this extension makes it send absolute-form
+/// instead, like `GET http://example.com/path HTTP/1.1`:
+///
+/// ```no_run
+/// use hyper::ext::ProxyMode;
+/// use hyper::{Body, Request};
+///
+/// let mut req = Request::get("http://example.com/path")
+///     .body(Body::empty())
+///     .unwrap();
+/// req.extensions_mut().insert(ProxyMode);
+/// ```
+///
+/// As with a proxied connector, `https` requests are still sent in
+/// origin-form, since they are expected to go through a tunnel.
+///
+/// [`Client`]: crate::Client
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProxyMode;
+
+/// A handle for a service to push responses to an HTTP/2 client.
+///
+/// Requests received by an HTTP/2 server carry this extension. Pushing a
+/// request and response pair sends a `PUSH_PROMISE` frame on the stream of
+/// the request it was found on, followed by the pushed response:
+///
+/// ```no_run
+/// use bytes::Bytes;
+/// use hyper::ext::PushSender;
+/// use hyper::{Body, Request, Response};
+/// use std::convert::Infallible;
+///
+/// const CSS: &[u8] = b"body { color: black; }";
+///
+/// async fn handle(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+///     if let Some(push) = req.extensions().get::<PushSender>() {
+///         let style = Request::get("https://example.com/style.css")
+///             .body(())
+///             .unwrap();
+///         let _ = push.push(style, Response::new(Bytes::from_static(CSS)));
+///     }
+///     Ok(Response::new(Body::from(
+///         r#"<link rel="stylesheet" href="/style.css">"#,
+///     )))
+/// }
+/// ```
+///
+/// Pushes are sent before the response to the original request, so they
+/// must be made before the service returns it. Pushed requests must be
+/// `GET` or `HEAD` without a body. Pushed streams are only opened once the
+/// client has advertised a `SETTINGS_MAX_CONCURRENT_STREAMS` limit, and a
+/// client may refuse pushes entirely. Either way, a push that can't be
+/// sent is dropped. At most 16 pushes can be waiting to be sent at a time,
+/// after which `push` returns an error.
+///
+/// HTTP/1 requests do not carry this extension, so a service that checks
+/// for it doesn't push anything over HTTP/1.
+#[derive(Clone, Debug)]
+pub struct PushSender {
+    queue: Arc<Mutex<PushQueue>>,
+}
+
+const MAX_QUEUED_PUSHES: usize = 16;
+
+type Push = (Request<()>, Response<Bytes>);
+
+#[derive(Debug)]
+struct PushQueue {
+    // A `Vec` doesn't allocate until the first push.
+    pushes: Vec<Push>,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+#[cfg(all(feature = "http2", feature = "server"))]
+pub(crate) struct PushReceiver {
+    queue: Arc<Mutex<PushQueue>>,
+}
+
+impl PushSender {
+    #[cfg(all(feature = "http2", feature = "server"))]
+    pub(crate) fn channel() -> (PushSender, PushReceiver) {
+        let queue = Arc::new(Mutex::new(PushQueue {
+            pushes: Vec::new(),
+            waker: None,
+            closed: false,
+        }));
+        (
+            PushSender {
+                queue: queue.clone(),
+            },
+            PushReceiver { queue },
+        )
+    }
+
+    /// Push a request and its response to the client.
+    ///
+    /// Returns an error if the response to the original request has
+    /// already been sent, or if too many pushes are waiting to be sent.
+    pub fn push(&self, request: Request<()>, response: Response<Bytes>) -> crate::Result<()> {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.closed {
+            return Err(crate::Error::new_closed());
+        }
+        if queue.pushes.len() >= MAX_QUEUED_PUSHES {
+            return Err(crate::Error::new_user_too_many_pushes());
+        }
+        queue.pushes.push((request, response));
+        if let Some(waker) = queue.waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "http2", feature = "server"))]
+impl PushReceiver {
+    /// Takes the queued pushes, or registers to be woken by the next one.
+    pub(crate) fn poll_take(&mut self, cx: &mut std::task::Context<'_>) -> Vec<Push> {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.pushes.is_empty() {
+            queue.waker = Some(cx.waker().clone());
+        }
+        std::mem::take(&mut queue.pushes)
+    }
+}
+
+#[cfg(all(feature = "http2", feature = "server"))]
+impl Drop for PushReceiver {
+    fn drop(&mut self) {
+        if let Ok(mut queue) = self.queue.lock() {
+            queue.closed = true;
+            queue.pushes.clear();
+            queue.waker = None;
+        }
+    }
+}
@@ -10,14 +10,22 @@ use http::{HeaderMap, Method, Version};
 use httparse::ParserConfig;
 use tokio::io::{AsyncRead, AsyncWrite};
 #[cfg(all(feature = "server", feature = "runtime"))]
-use tokio::time::Sleep;
+use tokio::time::{Instant, Sleep};
 use tracing::{debug, error, trace};
 
 use super::io::Buffered;
+#[cfg(feature = "server")]
+use super::UriValidator;
 use super::{Decoder, Encode, EncodedBuf, Encoder, Http1Transaction, ParseContext, Wants};
 use crate::body::DecodedLength;
+#[cfg(all(feature = "server", feature = "runtime"))]
+use crate::common::Future;
 use crate::common::{task, Pin, Poll, Unpin};
+#[cfg(feature = "server")]
+use crate::ext::ProxyAddrs;
 use crate::headers::connection_keep_alive;
+#[cfg(feature = "server")]
+use crate::proto::ResponseHook;
 use crate::proto::{BodyLength, MessageHead};
 
 const H2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
@@ -57,13 +65,57 @@ where
                 h1_header_read_timeout_fut: None,
                 #[cfg(all(feature = "server", feature = "runtime"))]
                 h1_header_read_timeout_running: false,
+                #[cfg(all(feature = "server", feature = "runtime"))]
+                write_flush_delay: None,
+                #[cfg(all(feature = "server", feature = "runtime"))]
+                write_flush_delay_fut: None,
+                #[cfg(all(feature = "server", feature = "runtime"))]
+                write_timeout: None,
+                #[cfg(all(feature = "server", feature = "runtime"))]
+                write_timeout_fut: None,
                 preserve_header_case: false,
                 title_case_headers: false,
                 h09_responses: false,
+                #[cfg(feature = "client")]
+                read_buf_prefilled: false,
+                max_body_size: None,
+                #[cfg(feature = "server")]
+                uri_validator: None,
+                #[cfg(feature = "server")]
+                require_host_header: false,
+                #[cfg(feature = "server")]
+                strict_expect: false,
+                #[cfg(feature = "server")]
+                reject_connect: false,
+                #[cfg(feature = "server")]
+                max_uri_len: None,
+                #[cfg(feature = "server")]
+                allow_obsolete_line_folding: false,
+                #[cfg(feature = "server")]
+                proxy_protocol: false,
+                #[cfg(feature = "server")]
+                proxy_addrs: None,
+                #[cfg(feature = "server")]
+                max_requests: None,
+                #[cfg(feature = "server")]
+                requests_read: 0,
+                #[cfg(feature = "server")]
+                on_response: None,
+                #[cfg(feature = "server")]
+                default_headers: HeaderMap::new(),
+                #[cfg(feature = "server")]
+                http10_no_content_length: false,
                 #[cfg(feature = "ffi")]
                 on_informational: None,
                 #[cfg(feature = "ffi")]
                 raw_headers: false,
+                read_trailers: None,
+                #[cfg(feature = "server")]
+                te_trailers: false,
+                #[cfg(feature = "server")]
+                upgrade_without_flush: false,
+                #[cfg(feature = "server")]
+                wrote_upgrade: false,
                 notify_read: false,
                 reading: Reading::Init,
                 writing: Writing::Init,
@@ -89,16 +141,25 @@ where
         self.io.set_max_buf_size(max);
     }
 
+    #[cfg(feature = "server")]
+    pub(crate) fn set_read_buf_initial_capacity(&mut self, cap: usize) {
+        self.io.set_read_buf_initial_capacity(cap);
+    }
+
     #[cfg(feature = "client")]
     pub(crate) fn set_read_buf_exact_size(&mut self, sz: usize) {
         self.io.set_read_buf_exact_size(sz);
     }
 
+    #[cfg(feature = "client")]
+    pub(crate) fn set_read_once_per_poll(&mut self) {
+        self.io.set_read_once_per_poll();
+    }
+
     pub(crate) fn set_write_strategy_flatten(&mut self) {
         self.io.set_write_strategy_flatten();
     }
 
-    #[cfg(feature = "client")]
     pub(crate) fn set_h1_parser_config(&mut self, parser_config: ParserConfig) {
         self.state.h1_parser_config = parser_config;
     }
@@ -116,16 +177,101 @@ where
         self.state.h09_responses = true;
     }
 
+    #[cfg(feature = "client")]
+    pub(crate) fn prefill_read_buf(&mut self, buf: &[u8]) {
+        self.io.read_buf_mut().extend_from_slice(buf);
+        self.state.read_buf_prefilled = true;
+    }
+
     #[cfg(all(feature = "server", feature = "runtime"))]
     pub(crate) fn set_http1_header_read_timeout(&mut self, val: Duration) {
         self.state.h1_header_read_timeout = Some(val);
     }
 
+    #[cfg(all(feature = "server", feature = "runtime"))]
+    pub(crate) fn set_write_flush_delay(&mut self, val: Duration) {
+        self.state.write_flush_delay = Some(val);
+    }
+
+    #[cfg(all(feature = "server", feature = "runtime"))]
+    pub(crate) fn set_write_timeout(&mut self, val: Duration) {
+        self.state.write_timeout = Some(val);
+    }
+
     #[cfg(feature = "server")]
     pub(crate) fn set_allow_half_close(&mut self) {
         self.state.allow_half_close = true;
     }
 
+    #[cfg(feature = "server")]
+    pub(crate) fn set_max_body_size(&mut self, max: u64) {
+        self.state.max_body_size = Some(max);
+    }
+
+    pub(crate) fn max_body_size(&self) -> Option<u64> {
+        self.state.max_body_size
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn set_uri_validator(&mut self, validator: UriValidator) {
+        self.state.uri_validator = Some(validator);
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn set_require_host_header(&mut self) {
+        self.state.require_host_header = true;
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn set_strict_expect(&mut self) {
+        self.state.strict_expect = true;
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn set_reject_connect(&mut self) {
+        self.state.reject_connect = true;
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn set_max_uri_len(&mut self, max: usize) {
+        self.state.max_uri_len = Some(max);
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn set_allow_obsolete_line_folding(&mut self) {
+        self.state.allow_obsolete_line_folding = true;
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn set_accept_proxy_protocol(&mut self) {
+        self.state.proxy_protocol = true;
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn set_max_requests(&mut self, max: usize) {
+        self.state.max_requests = Some(max);
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn set_upgrade_without_flush(&mut self) {
+        self.state.upgrade_without_flush = true;
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn set_on_response(&mut self, hook: ResponseHook) {
+        self.state.on_response = Some(hook);
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn set_default_headers(&mut self, headers: HeaderMap) {
+        self.state.default_headers = headers;
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn set_http10_no_content_length(&mut self) {
+        self.state.http10_no_content_length = true;
+    }
+
     #[cfg(feature = "ffi")]
     pub(crate) fn set_raw_headers(&mut self, enabled: bool) {
         self.state.raw_headers = enabled;
@@ -135,6 +281,10 @@ where
         self.io.into_inner()
     }
 
+    pub(crate) fn take_write_buf(&mut self) -> Bytes {
+        self.io.take_write_buf()
+    }
+
     pub(crate) fn pending_upgrade(&mut self) -> Option<crate::upgrade::Pending> {
         self.state.upgrade.take()
     }
@@ -187,7 +337,15 @@ where
         debug_assert!(self.can_read_head());
         trace!("Conn::read_head");
 
-        let msg = match ready!(self.io.parse::<T>(
+        #[cfg(feature = "server")]
+        if self.state.proxy_protocol {
+            if let Err(e) = ready!(self.poll_read_proxy_header(cx)) {
+                return self.on_read_head_error(e);
+            }
+        }
+
+        #[cfg_attr(not(feature = "server"), allow(unused_mut))]
+        let mut msg = match ready!(self.io.parse::<T>(
             cx,
             ParseContext {
                 cached_headers: &mut self.state.cached_headers,
@@ -201,6 +359,18 @@ where
                 h1_header_read_timeout_running: &mut self.state.h1_header_read_timeout_running,
                 preserve_header_case: self.state.preserve_header_case,
                 h09_responses: self.state.h09_responses,
+                #[cfg(feature = "server")]
+                uri_validator: self.state.uri_validator.as_ref(),
+                #[cfg(feature = "server")]
+                require_host_header: self.state.require_host_header,
+                #[cfg(feature = "server")]
+                strict_expect: self.state.strict_expect,
+                #[cfg(feature = "server")]
+                reject_connect: self.state.reject_connect,
+                #[cfg(feature = "server")]
+                max_uri_len: self.state.max_uri_len,
+                #[cfg(feature = "server")]
+                allow_obsolete_line_folding: self.state.allow_obsolete_line_folding,
                 #[cfg(feature = "ffi")]
                 on_informational: &mut self.state.on_informational,
                 #[cfg(feature = "ffi")]
@@ -219,6 +389,29 @@ where
         // Prevent accepting HTTP/0.9 responses after the initial one, if any.
         self.state.h09_responses = false;
 
+        #[cfg(feature = "client")]
+        {
+            self.state.read_buf_prefilled = false;
+        }
+
+        #[cfg(feature = "server")]
+        if let Some(addrs) = self.state.proxy_addrs {
+            msg.head.extensions.insert(addrs);
+        }
+
+        // A body that declares a length over the limit can be rejected
+        // before any of it is read.
+        if let (Some(max), Some(len)) = (self.state.max_body_size, msg.decode.into_opt()) {
+            if len > max {
+                debug!("incoming body length {} exceeds limit {}", len, max);
+                self.close_read();
+                return match self.on_parse_error(crate::Error::new_body_too_large()) {
+                    Ok(()) => Poll::Pending,
+                    Err(e) => Poll::Ready(Some(Err(e))),
+                };
+            }
+        }
+
         // Drop any OnInformational callbacks, we're done there!
         #[cfg(feature = "ffi")]
         {
@@ -229,6 +422,20 @@ where
         self.state.keep_alive &= msg.keep_alive;
         self.state.version = msg.head.version;
 
+        #[cfg(feature = "server")]
+        if T::is_server() {
+            self.state.te_trailers = crate::headers::te_trailers(&msg.head.headers);
+        }
+
+        #[cfg(feature = "server")]
+        if let Some(max) = self.state.max_requests {
+            self.state.requests_read += 1;
+            if self.state.requests_read >= max {
+                debug!("read {} requests, closing connection after response", max);
+                self.state.disable_keep_alive();
+            }
+        }
+
         let mut wants = if msg.wants_upgrade {
             Wants::UPGRADE
         } else {
@@ -253,6 +460,66 @@ where
         Poll::Ready(Some(Ok((msg.head, msg.decode, wants))))
     }
 
+    #[cfg(feature = "server")]
+    fn poll_read_proxy_header(&mut self, cx: &mut task::Context<'_>) -> Poll<crate::Result<()>> {
+        loop {
+            if let Some(addrs) = super::proxy::parse(self.io.read_buf_mut())? {
+                self.state.proxy_protocol = false;
+                self.state.proxy_addrs = addrs;
+                return Poll::Ready(Ok(()));
+            }
+            #[cfg(feature = "runtime")]
+            if !self.io.read_buf().is_empty() {
+                self.poll_proxy_header_timeout(cx)?;
+            }
+            if ready!(self.io.poll_read_from_io(cx)).map_err(crate::Error::new_io)? == 0 {
+                trace!("proxy header eof");
+                return Poll::Ready(Err(crate::Error::new_incomplete()));
+            }
+        }
+    }
+
+    /// Arms the header read timer once the PROXY header has started to
+    /// arrive, and errors if it has elapsed.
+    ///
+    /// The timer is left running once the PROXY header is read, so the same
+    /// deadline also covers the request head that follows it.
+    #[cfg(all(feature = "server", feature = "runtime"))]
+    fn poll_proxy_header_timeout(&mut self, cx: &mut task::Context<'_>) -> crate::Result<()> {
+        let timeout = match self.state.h1_header_read_timeout {
+            Some(timeout) => timeout,
+            None => return Ok(()),
+        };
+
+        if !self.state.h1_header_read_timeout_running {
+            let deadline = Instant::now() + timeout;
+            match self.state.h1_header_read_timeout_fut {
+                Some(ref mut fut) => {
+                    debug!("resetting h1 header read timeout timer");
+                    fut.as_mut().reset(deadline);
+                }
+                None => {
+                    debug!("setting h1 header read timeout timer");
+                    self.state.h1_header_read_timeout_fut =
+                        Some(Box::pin(tokio::time::sleep_until(deadline)));
+                }
+            }
+            self.state.h1_header_read_timeout_running = true;
+        }
+
+        let fut = self
+            .state
+            .h1_header_read_timeout_fut
+            .as_mut()
+            .expect("just set");
+        if fut.as_mut().poll(cx).is_ready() {
+            self.state.h1_header_read_timeout_running = false;
+            tracing::warn!("read proxy header from client timeout");
+            return Err(crate::Error::new_header_timeout());
+        }
+        Ok(())
+    }
+
     fn on_read_head_error<Z>(&mut self, e: crate::Error) -> Poll<Option<crate::Result<Z>>> {
         // If we are currently waiting on a message, then an empty
         // message should be reported as an error. If not, it is just
@@ -291,6 +558,7 @@ where
                     Ok(slice) => {
                         let (reading, chunk) = if decoder.is_eof() {
                             debug!("incoming body completed");
+                            self.state.read_trailers = decoder.take_trailers();
                             (
                                 Reading::KeepAlive,
                                 if !slice.is_empty() {
@@ -336,6 +604,11 @@ where
         ret
     }
 
+    /// Takes the trailer fields received after the incoming body, if any.
+    pub(crate) fn take_read_trailers(&mut self) -> Option<HeaderMap> {
+        self.state.read_trailers.take()
+    }
+
     pub(crate) fn wants_read_again(&mut self) -> bool {
         let ret = self.state.notify_read;
         self.state.notify_read = false;
@@ -374,6 +647,12 @@ where
         debug_assert!(T::is_client());
 
         if !self.io.read_buf().is_empty() {
+            // Bytes given to the handshake belong to the first response, so
+            // wait for the request to be written.
+            #[cfg(feature = "client")]
+            if self.state.read_buf_prefilled {
+                return Poll::Pending;
+            }
             debug!("received an unexpected {} bytes", self.io.read_buf().len());
             return Poll::Ready(Err(crate::Error::new_unexpected_message()));
         }
@@ -547,8 +826,32 @@ where
             self.state.busy();
         }
 
+        // A close-delimited HTTP/1.0 response ends the connection.
+        #[cfg(feature = "server")]
+        if self.state.http10_no_content_length && self.state.version == Version::HTTP_10 {
+            self.state.disable_keep_alive();
+        }
+
         self.enforce_version(&mut head);
 
+        #[cfg(feature = "server")]
+        for (name, value) in &self.state.default_headers {
+            head.headers.entry(name).or_insert_with(|| value.clone());
+        }
+
+        #[cfg(feature = "server")]
+        if self.state.reached_max_requests() {
+            head.headers
+                .insert(CONNECTION, HeaderValue::from_static("close"));
+        }
+
+        #[cfg(feature = "server")]
+        {
+            self.state.wrote_upgrade = self.state.upgrade_without_flush
+                && self.state.upgrade.is_some()
+                && T::is_upgrade(&head.subject, &self.state.method);
+        }
+
         let buf = self.io.headers_buf();
         match super::role::encode_headers::<T>(
             Encode {
@@ -558,6 +861,10 @@ where
                 keep_alive: self.state.wants_keep_alive(),
                 req_method: &mut self.state.method,
                 title_case_headers: self.state.title_case_headers,
+                #[cfg(feature = "server")]
+                on_response: self.state.on_response.as_ref(),
+                #[cfg(feature = "server")]
+                http10_no_content_length: self.state.http10_no_content_length,
             },
             buf,
         ) {
@@ -701,6 +1008,36 @@ where
         res
     }
 
+    pub(crate) fn write_trailers(&mut self, trailers: HeaderMap) -> crate::Result<()> {
+        debug_assert!(self.can_write_body());
+
+        #[cfg(feature = "server")]
+        if T::is_server() && !self.state.te_trailers {
+            debug!("request didn't send `TE: trailers`, ignoring trailers");
+            return self.end_body();
+        }
+
+        let state = match self.state.writing {
+            Writing::Body(ref encoder) => {
+                match encoder.encode_trailers(trailers, self.state.title_case_headers) {
+                    Some(end) => {
+                        self.io.buffer(end);
+                        if encoder.is_last() || encoder.is_close_delimited() {
+                            Writing::Closed
+                        } else {
+                            Writing::KeepAlive
+                        }
+                    }
+                    None => return self.end_body(),
+                }
+            }
+            _ => return Ok(()),
+        };
+
+        self.state.writing = state;
+        Ok(())
+    }
+
     // When we get a parse error, depending on what side we are, we might be able
     // to write a response before closing the connection.
     //
@@ -726,12 +1063,84 @@ where
     }
 
     pub(crate) fn poll_flush(&mut self, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
-        ready!(Pin::new(&mut self.io).poll_flush(cx))?;
+        #[cfg(all(feature = "server", feature = "runtime"))]
+        ready!(self.poll_write_flush_delay(cx));
+        #[cfg(feature = "server")]
+        if self.state.wrote_upgrade {
+            // The upgraded IO writes whatever is left before any new data,
+            // so don't wait on a slow peer to hand the connection off.
+            if let Poll::Ready(Err(e)) = self.poll_flush_io(cx) {
+                return Poll::Ready(Err(e));
+            }
+            self.try_keep_alive(cx);
+            return Poll::Ready(Ok(()));
+        }
+        ready!(self.poll_flush_io(cx))?;
         self.try_keep_alive(cx);
         trace!("flushed({}): {:?}", T::LOG, self.state);
         Poll::Ready(Ok(()))
     }
 
+    /// Flushes the IO, failing if the write timeout passes without any
+    /// bytes being written.
+    fn poll_flush_io(&mut self, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        #[cfg(all(feature = "server", feature = "runtime"))]
+        if let Some(timeout) = self.state.write_timeout {
+            let before = self.io.write_buf().remaining();
+            let res = Pin::new(&mut self.io).poll_flush(cx);
+            if res.is_ready() {
+                self.state.write_timeout_fut = None;
+                return res;
+            }
+
+            let progressed = self.io.write_buf().remaining() < before;
+            match self.state.write_timeout_fut {
+                Some(ref mut fut) if progressed => {
+                    fut.as_mut().reset(tokio::time::Instant::now() + timeout);
+                }
+                Some(_) => (),
+                None => {
+                    self.state.write_timeout_fut = Some(Box::pin(tokio::time::sleep(timeout)));
+                }
+            }
+
+            let fut = self.state.write_timeout_fut.as_mut().expect("just set");
+            if fut.as_mut().poll(cx).is_ready() {
+                debug!("write timed out after {:?}", timeout);
+                self.state.write_timeout_fut = None;
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, WriteTimedOut)));
+            }
+            return Poll::Pending;
+        }
+
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    /// Holds back a flush while a body is mid-write and the buffer has room,
+    /// until the configured delay has passed.
+    #[cfg(all(feature = "server", feature = "runtime"))]
+    fn poll_write_flush_delay(&mut self, cx: &mut task::Context<'_>) -> Poll<()> {
+        let delay = match self.state.write_flush_delay {
+            Some(delay) => delay,
+            None => return Poll::Ready(()),
+        };
+
+        let writing_body = matches!(self.state.writing, Writing::Body(..));
+        if !writing_body || !self.io.can_buffer() || self.io.write_buf().remaining() == 0 {
+            self.state.write_flush_delay_fut = None;
+            return Poll::Ready(());
+        }
+
+        let sleep = self
+            .state
+            .write_flush_delay_fut
+            .get_or_insert_with(|| Box::pin(tokio::time::sleep(delay)));
+        ready!(sleep.as_mut().poll(cx));
+        trace!("write flush delay elapsed");
+        self.state.write_flush_delay_fut = None;
+        Poll::Ready(())
+    }
+
     pub(crate) fn poll_shutdown(&mut self, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
         match ready!(Pin::new(self.io.io_mut()).poll_shutdown(cx)) {
             Ok(()) => {
@@ -747,6 +1156,13 @@ where
 
     /// If the read side can be cheaply drained, do so. Otherwise, close.
     pub(super) fn poll_drain_or_close_read(&mut self, cx: &mut task::Context<'_>) {
+        // The body was never asked for, so don't invite the client to send
+        // it with a `100 Continue`. Anything it sent anyway is still drained.
+        if let Reading::Continue(ref decoder) = self.state.reading {
+            trace!("body dropped before 100 Continue was sent");
+            self.state.reading = Reading::Body(decoder.clone());
+        }
+
         let _ = self.poll_read_body(cx);
 
         // If still in Reading::Body, just give up
@@ -804,6 +1220,25 @@ impl<I, B: Buf, T> fmt::Debug for Conn<I, B, T> {
 // B and T are never pinned
 impl<I: Unpin, B, T> Unpin for Conn<I, B, T> {}
 
+/// Error returned when a write made no progress within the write timeout.
+#[cfg(all(feature = "server", feature = "runtime"))]
+#[derive(Debug)]
+struct WriteTimedOut;
+
+#[cfg(all(feature = "server", feature = "runtime"))]
+impl fmt::Display for WriteTimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("write to client timed out")
+    }
+}
+
+#[cfg(all(feature = "server", feature = "runtime"))]
+impl std::error::Error for WriteTimedOut {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&crate::error::TimedOut)
+    }
+}
+
 struct State {
     allow_half_close: bool,
     /// Re-usable HeaderMap to reduce allocating new ones.
@@ -825,9 +1260,69 @@ struct State {
     h1_header_read_timeout_fut: Option<Pin<Box<Sleep>>>,
     #[cfg(all(feature = "server", feature = "runtime"))]
     h1_header_read_timeout_running: bool,
+    /// How long to hold back flushing while writing a body, so that small
+    /// chunks can be coalesced.
+    #[cfg(all(feature = "server", feature = "runtime"))]
+    write_flush_delay: Option<Duration>,
+    #[cfg(all(feature = "server", feature = "runtime"))]
+    write_flush_delay_fut: Option<Pin<Box<Sleep>>>,
+    /// How long a flush may make no progress before the connection
+    /// is closed.
+    #[cfg(all(feature = "server", feature = "runtime"))]
+    write_timeout: Option<Duration>,
+    #[cfg(all(feature = "server", feature = "runtime"))]
+    write_timeout_fut: Option<Pin<Box<Sleep>>>,
     preserve_header_case: bool,
     title_case_headers: bool,
     h09_responses: bool,
+    /// Whether the read buffer was seeded with bytes read before the
+    /// handshake, which are the start of the first response.
+    #[cfg(feature = "client")]
+    read_buf_prefilled: bool,
+    /// The largest incoming body allowed, if limited.
+    max_body_size: Option<u64>,
+    /// If set, called with each parsed request-target; a `false` return
+    /// rejects the request as a bad URI.
+    #[cfg(feature = "server")]
+    uri_validator: Option<UriValidator>,
+    /// Whether HTTP/1.1 requests without a `Host` header are rejected.
+    #[cfg(feature = "server")]
+    require_host_header: bool,
+    /// Whether requests with an unsupported `Expect` header are rejected.
+    #[cfg(feature = "server")]
+    strict_expect: bool,
+    /// Whether `CONNECT` requests are rejected.
+    #[cfg(feature = "server")]
+    reject_connect: bool,
+    /// The longest request-target allowed, if lower than the built-in limit.
+    #[cfg(feature = "server")]
+    max_uri_len: Option<usize>,
+    /// Whether obsolete line folding in request headers is unfolded
+    /// instead of rejected.
+    #[cfg(feature = "server")]
+    allow_obsolete_line_folding: bool,
+    /// Whether a PROXY protocol header is expected before the first request.
+    #[cfg(feature = "server")]
+    proxy_protocol: bool,
+    /// The addresses announced by the PROXY protocol header, if any.
+    #[cfg(feature = "server")]
+    proxy_addrs: Option<ProxyAddrs>,
+    /// The number of requests to read before closing, if limited.
+    #[cfg(feature = "server")]
+    max_requests: Option<usize>,
+    /// The number of requests read on this connection so far.
+    #[cfg(feature = "server")]
+    requests_read: usize,
+    /// If set, called with each response head before it is written.
+    #[cfg(feature = "server")]
+    on_response: Option<ResponseHook>,
+    /// Headers to add to responses that lack them.
+    #[cfg(feature = "server")]
+    default_headers: HeaderMap,
+    /// Whether HTTP/1.0 responses are framed by closing the connection,
+    /// instead of with a `Content-Length`.
+    #[cfg(feature = "server")]
+    http10_no_content_length: bool,
     /// If set, called with each 1xx informational response received for
     /// the current request. MUST be unset after a non-1xx response is
     /// received.
@@ -835,6 +1330,21 @@ struct State {
     on_informational: Option<crate::ffi::OnInformational>,
     #[cfg(feature = "ffi")]
     raw_headers: bool,
+    /// Trailer fields received after the last incoming chunked body,
+    /// until the Dispatcher takes them.
+    read_trailers: Option<HeaderMap>,
+    /// Whether the current request accepts trailer fields in the response,
+    /// by sending `TE: trailers`.
+    #[cfg(feature = "server")]
+    te_trailers: bool,
+    /// Whether an upgrade may complete before the response switching
+    /// protocols has been flushed, leaving the rest for the upgraded IO.
+    #[cfg(feature = "server")]
+    upgrade_without_flush: bool,
+    /// Whether a response switching protocols was written, with
+    /// `upgrade_without_flush` set.
+    #[cfg(feature = "server")]
+    wrote_upgrade: bool,
     /// Set to true when the Dispatcher should poll read operations
     /// again. See the `maybe_notify` method for more.
     notify_read: bool,
@@ -991,6 +1501,14 @@ impl State {
         self.keep_alive.disable()
     }
 
+    #[cfg(feature = "server")]
+    fn reached_max_requests(&self) -> bool {
+        match self.max_requests {
+            Some(max) => self.requests_read >= max,
+            None => false,
+        }
+    }
+
     fn busy(&mut self) {
         if let KA::Disabled = self.keep_alive.status() {
             return;
@@ -3,7 +3,8 @@ use std::fmt;
 use std::io;
 use std::usize;
 
-use bytes::Bytes;
+use bytes::{BufMut, Bytes, BytesMut};
+use http::header::{HeaderMap, HeaderName, HeaderValue};
 use tracing::{debug, trace};
 
 use crate::common::{task, Poll};
@@ -13,6 +14,11 @@ use super::DecodedLength;
 
 use self::Kind::{Chunked, Eof, Length};
 
+/// Maximum amount of bytes allowed in chunked trailers.
+const TRAILER_LIMIT: usize = 1024 * 16;
+/// Maximum number of chunked trailer fields.
+const MAX_TRAILERS: usize = 100;
+
 /// Decoders to handle different Transfer-Encodings.
 ///
 /// If a message body does not include a Transfer-Encoding, it *should*
@@ -20,6 +26,10 @@ use self::Kind::{Chunked, Eof, Length};
 #[derive(Clone, PartialEq)]
 pub(crate) struct Decoder {
     kind: Kind,
+    /// Raw trailer fields of a chunked body, while they are being read.
+    trailers_buf: Option<BytesMut>,
+    /// Trailer fields of a chunked body, once it has ended.
+    trailers: Option<HeaderMap>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -69,18 +79,24 @@ impl Decoder {
     pub(crate) fn length(x: u64) -> Decoder {
         Decoder {
             kind: Kind::Length(x),
+            trailers_buf: None,
+            trailers: None,
         }
     }
 
     pub(crate) fn chunked() -> Decoder {
         Decoder {
             kind: Kind::Chunked(ChunkedState::Size, 0),
+            trailers_buf: None,
+            trailers: None,
         }
     }
 
     pub(crate) fn eof() -> Decoder {
         Decoder {
             kind: Kind::Eof(false),
+            trailers_buf: None,
+            trailers: None,
         }
     }
 
@@ -98,6 +114,11 @@ impl Decoder {
         matches!(self.kind, Length(0) | Chunked(ChunkedState::End, _) | Eof(true))
     }
 
+    /// Takes the trailer fields received after a chunked body, if any.
+    pub(crate) fn take_trailers(&mut self) -> Option<HeaderMap> {
+        self.trailers.take()
+    }
+
     pub(crate) fn decode<R: MemRead>(
         &mut self,
         cx: &mut task::Context<'_>,
@@ -129,9 +150,12 @@ impl Decoder {
                 loop {
                     let mut buf = None;
                     // advances the chunked state
-                    *state = ready!(state.step(cx, body, size, &mut buf))?;
+                    *state = ready!(state.step(cx, body, size, &mut buf, &mut self.trailers_buf))?;
                     if *state == ChunkedState::End {
                         trace!("end of chunked");
+                        if let Some(mut trailers_buf) = self.trailers_buf.take() {
+                            self.trailers = Some(decode_trailers(&mut trailers_buf)?);
+                        }
                         return Poll::Ready(Ok(Bytes::new()));
                     }
                     if let Some(buf) = buf {
@@ -186,6 +210,7 @@ impl ChunkedState {
         body: &mut R,
         size: &mut u64,
         buf: &mut Option<Bytes>,
+        trailers_buf: &mut Option<BytesMut>,
     ) -> Poll<Result<ChunkedState, io::Error>> {
         use self::ChunkedState::*;
         match *self {
@@ -196,9 +221,9 @@ impl ChunkedState {
             Body => ChunkedState::read_body(cx, body, size, buf),
             BodyCr => ChunkedState::read_body_cr(cx, body),
             BodyLf => ChunkedState::read_body_lf(cx, body),
-            Trailer => ChunkedState::read_trailer(cx, body),
-            TrailerLf => ChunkedState::read_trailer_lf(cx, body),
-            EndCr => ChunkedState::read_end_cr(cx, body),
+            Trailer => ChunkedState::read_trailer(cx, body, trailers_buf),
+            TrailerLf => ChunkedState::read_trailer_lf(cx, body, trailers_buf),
+            EndCr => ChunkedState::read_end_cr(cx, body, trailers_buf),
             EndLf => ChunkedState::read_end_lf(cx, body),
             End => Poll::Ready(Ok(ChunkedState::End)),
         }
@@ -368,9 +393,12 @@ impl ChunkedState {
     fn read_trailer<R: MemRead>(
         cx: &mut task::Context<'_>,
         rdr: &mut R,
+        trailers_buf: &mut Option<BytesMut>,
     ) -> Poll<Result<ChunkedState, io::Error>> {
         trace!("read_trailer");
-        match byte!(rdr, cx) {
+        let byte = byte!(rdr, cx);
+        put_trailer_byte(trailers_buf, byte)?;
+        match byte {
             b'\r' => Poll::Ready(Ok(ChunkedState::TrailerLf)),
             _ => Poll::Ready(Ok(ChunkedState::Trailer)),
         }
@@ -378,9 +406,13 @@ impl ChunkedState {
     fn read_trailer_lf<R: MemRead>(
         cx: &mut task::Context<'_>,
         rdr: &mut R,
+        trailers_buf: &mut Option<BytesMut>,
     ) -> Poll<Result<ChunkedState, io::Error>> {
         match byte!(rdr, cx) {
-            b'\n' => Poll::Ready(Ok(ChunkedState::EndCr)),
+            b'\n' => {
+                put_trailer_byte(trailers_buf, b'\n')?;
+                Poll::Ready(Ok(ChunkedState::EndCr))
+            }
             _ => Poll::Ready(Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
                 "Invalid trailer end LF",
@@ -391,10 +423,14 @@ impl ChunkedState {
     fn read_end_cr<R: MemRead>(
         cx: &mut task::Context<'_>,
         rdr: &mut R,
+        trailers_buf: &mut Option<BytesMut>,
     ) -> Poll<Result<ChunkedState, io::Error>> {
         match byte!(rdr, cx) {
             b'\r' => Poll::Ready(Ok(ChunkedState::EndLf)),
-            _ => Poll::Ready(Ok(ChunkedState::Trailer)),
+            byte => {
+                put_trailer_byte(trailers_buf, byte)?;
+                Poll::Ready(Ok(ChunkedState::Trailer))
+            }
         }
     }
     fn read_end_lf<R: MemRead>(
@@ -411,6 +447,44 @@ impl ChunkedState {
     }
 }
 
+fn put_trailer_byte(trailers_buf: &mut Option<BytesMut>, byte: u8) -> io::Result<()> {
+    let buf = trailers_buf.get_or_insert_with(BytesMut::new);
+    if buf.len() >= TRAILER_LIMIT {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "chunk trailers bytes over limit",
+        ));
+    }
+    buf.put_u8(byte);
+    Ok(())
+}
+
+fn decode_trailers(buf: &mut BytesMut) -> io::Result<HeaderMap> {
+    // The final CRLF isn't kept while reading, but httparse needs it to
+    // know the fields are complete.
+    buf.extend_from_slice(b"\r\n");
+
+    let mut headers = [httparse::EMPTY_HEADER; MAX_TRAILERS];
+    match httparse::parse_headers(buf, &mut headers) {
+        Ok(httparse::Status::Complete((_, headers))) => {
+            let mut trailers = HeaderMap::with_capacity(headers.len());
+            for header in headers {
+                let name = HeaderName::from_bytes(header.name.as_bytes())
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                let value = HeaderValue::from_bytes(header.value)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                trailers.append(name, value);
+            }
+            Ok(trailers)
+        }
+        Ok(httparse::Status::Partial) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid chunk trailers",
+        )),
+        Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+    }
+}
+
 #[derive(Debug)]
 struct IncompleteBody;
 
@@ -480,9 +554,10 @@ mod tests {
             let rdr = &mut s.as_bytes();
             let mut size = 0;
             loop {
-                let result =
-                    futures_util::future::poll_fn(|cx| state.step(cx, rdr, &mut size, &mut None))
-                        .await;
+                let result = futures_util::future::poll_fn(|cx| {
+                    state.step(cx, rdr, &mut size, &mut None, &mut None)
+                })
+                .await;
                 let desc = format!("read_size failed for {:?}", s);
                 state = result.expect(desc.as_str());
                 if state == ChunkedState::Body || state == ChunkedState::EndCr {
@@ -497,9 +572,10 @@ mod tests {
             let rdr = &mut s.as_bytes();
             let mut size = 0;
             loop {
-                let result =
-                    futures_util::future::poll_fn(|cx| state.step(cx, rdr, &mut size, &mut None))
-                        .await;
+                let result = futures_util::future::poll_fn(|cx| {
+                    state.step(cx, rdr, &mut size, &mut None, &mut None)
+                })
+                .await;
                 state = match result {
                     Ok(s) => s,
                     Err(e) => {
@@ -595,6 +671,55 @@ mod tests {
         assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
     }
 
+    #[tokio::test]
+    async fn test_read_chunked_trailers() {
+        let mut mock_buf = &b"\
+            5\r\nhello\r\n\
+            0\r\n\
+            x-checksum: abc\r\n\
+            x-more: 1\r\n\
+            \r\n\
+        "[..];
+        let mut decoder = Decoder::chunked();
+        assert_eq!(decoder.decode_fut(&mut mock_buf).await.unwrap(), "hello");
+        assert!(decoder.take_trailers().is_none());
+        assert!(decoder.decode_fut(&mut mock_buf).await.unwrap().is_empty());
+        assert!(decoder.is_eof());
+
+        let trailers = decoder.take_trailers().expect("trailers");
+        assert_eq!(trailers.len(), 2);
+        assert_eq!(trailers["x-checksum"], "abc");
+        assert_eq!(trailers["x-more"], "1");
+    }
+
+    #[tokio::test]
+    async fn test_read_chunked_malformed_trailers() {
+        let mut mock_buf = &b"\
+            5\r\nhello\r\n\
+            0\r\n\
+            bad trailer\r\n\
+            \r\n\
+        "[..];
+        let mut decoder = Decoder::chunked();
+        assert_eq!(decoder.decode_fut(&mut mock_buf).await.unwrap(), "hello");
+        let e = decoder.decode_fut(&mut mock_buf).await.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(decoder.take_trailers().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_read_chunked_trailers_over_limit() {
+        let mut body = b"0\r\nx-big: ".to_vec();
+        body.resize(body.len() + TRAILER_LIMIT, b'a');
+        body.extend_from_slice(b"\r\n\r\n");
+        let mut mock_buf = &body[..];
+        let e = Decoder::chunked()
+            .decode_fut(&mut mock_buf)
+            .await
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
     #[tokio::test]
     async fn test_read_chunked_after_eof() {
         let mut mock_buf = &b"10\r\n1234567890abcdef\r\n0\r\n\r\n"[..];
@@ -17,6 +17,8 @@ pub(crate) struct Dispatcher<D, Bs: HttpBody, I, T> {
     conn: Conn<I, Bs::Data, T>,
     dispatch: D,
     body_tx: Option<crate::body::Sender>,
+    /// How many more bytes the incoming body may have, if it is limited.
+    body_remaining: Option<u64>,
     body_rx: Pin<Box<Option<Bs>>>,
     is_closing: bool,
 }
@@ -75,6 +77,7 @@ where
             conn,
             dispatch,
             body_tx: None,
+            body_remaining: None,
             body_rx: Box::pin(None),
             is_closing: false,
         }
@@ -93,6 +96,28 @@ where
         (io, buf, self.dispatch)
     }
 
+    /// Lets an upgrade complete before the response has been flushed.
+    #[cfg(feature = "server")]
+    pub(crate) fn set_upgrade_without_flush(&mut self) {
+        self.conn.set_upgrade_without_flush();
+    }
+
+    /// Takes any bytes queued for writing that haven't been flushed yet.
+    pub(crate) fn take_write_buf(&mut self) -> Bytes {
+        self.conn.take_write_buf()
+    }
+
+    /// Write any queued messages, and flush them to the IO, without
+    /// reading anything.
+    #[cfg(feature = "client")]
+    pub(crate) fn poll_write_flush(
+        &mut self,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<crate::Result<()>> {
+        let _ = self.poll_write(cx)?;
+        self.poll_flush(cx)
+    }
+
     /// Run this dispatcher until HTTP says this connection is done,
     /// but don't call `AsyncWrite::shutdown` on the underlying IO.
     ///
@@ -203,19 +228,34 @@ where
                         }
                     }
                     match self.conn.poll_read_body(cx) {
-                        Poll::Ready(Some(Ok(chunk))) => match body.try_send_data(chunk) {
-                            Ok(()) => {
-                                self.body_tx = Some(body);
-                            }
-                            Err(_canceled) => {
-                                if self.conn.can_read_body() {
-                                    trace!("body receiver dropped before eof, closing");
+                        Poll::Ready(Some(Ok(chunk))) => {
+                            if let Some(ref mut remaining) = self.body_remaining {
+                                let len = chunk.len() as u64;
+                                if len > *remaining {
+                                    debug!("incoming body exceeded the size limit, closing");
+                                    body.send_error(crate::Error::new_body_too_large());
                                     self.conn.close_read();
+                                    continue;
                                 }
+                                *remaining -= len;
                             }
-                        },
+                            match body.try_send_data(chunk) {
+                                Ok(()) => {
+                                    self.body_tx = Some(body);
+                                }
+                                Err(_canceled) => {
+                                    if self.conn.can_read_body() {
+                                        trace!("body receiver dropped before eof, closing");
+                                        self.conn.close_read();
+                                    }
+                                }
+                            }
+                        }
                         Poll::Ready(None) => {
-                            // just drop, the body will close automatically
+                            if let Some(trailers) = self.conn.take_read_trailers() {
+                                body.send_trailers_now(trailers);
+                            }
+                            // drop, the body will close automatically
                         }
                         Poll::Pending => {
                             self.body_tx = Some(body);
@@ -252,6 +292,7 @@ where
                     other => {
                         let (tx, rx) = Body::new_channel(other, wants.contains(Wants::EXPECT));
                         self.body_tx = Some(tx);
+                        self.body_remaining = self.conn.max_body_size();
                         rx
                     }
                 };
@@ -301,7 +342,7 @@ where
                     //
                     // If so, we can skip a bit of bookkeeping that streaming
                     // bodies need to do.
-                    if let Some(full) = crate::body::take_full_data(&mut body) {
+                    if let Some(full) = crate::body::take_full_data(&mut body, cx) {
                         self.conn.write_full_msg(head, full);
                         return Poll::Ready(Ok(()));
                     }
@@ -363,8 +404,15 @@ where
                             self.conn.write_body(chunk);
                         }
                     } else {
+                        let trailers = ready!(body.as_mut().poll_trailers(cx)).map_err(|e| {
+                            *clear_body = true;
+                            crate::Error::new_user_body(e)
+                        })?;
                         *clear_body = true;
-                        self.conn.end_body()?;
+                        match trailers {
+                            Some(trailers) => self.conn.write_trailers(trailers)?,
+                            None => self.conn.end_body()?,
+                        }
                     }
                 } else {
                     return Poll::Pending;
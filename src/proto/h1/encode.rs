@@ -2,13 +2,39 @@ use std::fmt;
 use std::io::IoSlice;
 
 use bytes::buf::{Chain, Take};
-use bytes::Buf;
-use tracing::trace;
+use bytes::{Buf, Bytes};
+use http::header::{
+    HeaderName, AUTHORIZATION, CACHE_CONTROL, CONNECTION, CONTENT_ENCODING, CONTENT_LENGTH,
+    CONTENT_RANGE, CONTENT_TYPE, HOST, MAX_FORWARDS, SET_COOKIE, TE, TRAILER, TRANSFER_ENCODING,
+    UPGRADE,
+};
+use http::HeaderMap;
+use tracing::{debug, trace};
 
 use super::io::WriteBuf;
+use super::role::{write_headers, write_headers_title_case};
 
 type StaticBuf = &'static [u8];
 
+/// Fields that must not be sent as trailers, since they are needed to frame,
+/// route or process the message before its body (RFC 7230, section 4.1.2).
+const INVALID_TRAILER_FIELDS: [HeaderName; 14] = [
+    AUTHORIZATION,
+    CACHE_CONTROL,
+    CONNECTION,
+    CONTENT_ENCODING,
+    CONTENT_LENGTH,
+    CONTENT_RANGE,
+    CONTENT_TYPE,
+    HOST,
+    MAX_FORWARDS,
+    SET_COOKIE,
+    TE,
+    TRAILER,
+    TRANSFER_ENCODING,
+    UPGRADE,
+];
+
 /// Encoders to handle different Transfer-Encodings.
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct Encoder {
@@ -46,6 +72,7 @@ enum BufKind<B> {
     Limited(Take<B>),
     Chunked(Chain<Chain<ChunkSize, B>, StaticBuf>),
     ChunkedEnd(StaticBuf),
+    Trailers(Bytes),
 }
 
 impl Encoder {
@@ -102,6 +129,49 @@ impl Encoder {
         }
     }
 
+    /// Encodes the last chunk followed by the given trailer fields.
+    ///
+    /// Trailer names are written like header names, in title case if
+    /// `title_case_headers` is set. Fields that aren't allowed in trailers,
+    /// such as `Content-Length` or `Host`, are dropped. Only chunked bodies
+    /// can carry trailers.
+    /// For any other kind, `None` is returned and the caller should end the
+    /// body normally.
+    pub(crate) fn encode_trailers<B>(
+        &self,
+        mut trailers: HeaderMap,
+        title_case_headers: bool,
+    ) -> Option<EncodedBuf<B>> {
+        match self.kind {
+            Kind::Chunked => {
+                for name in &INVALID_TRAILER_FIELDS {
+                    if trailers.remove(name).is_some() {
+                        debug!("dropping invalid trailer field: {}", name);
+                    }
+                }
+
+                let mut buf = Vec::with_capacity(64);
+                buf.extend_from_slice(b"0\r\n");
+                if title_case_headers {
+                    write_headers_title_case(&trailers, &mut buf);
+                } else {
+                    write_headers(&trailers, &mut buf);
+                }
+                buf.extend_from_slice(b"\r\n");
+                Some(EncodedBuf {
+                    kind: BufKind::Trailers(Bytes::from(buf)),
+                })
+            }
+            _ => {
+                debug!(
+                    "trailers are not supported for {:?} encoding, ignoring",
+                    self.kind
+                );
+                None
+            }
+        }
+    }
+
     pub(crate) fn encode<B>(&mut self, msg: B) -> EncodedBuf<B>
     where
         B: Buf,
@@ -226,6 +296,7 @@ where
             BufKind::Limited(ref b) => b.remaining(),
             BufKind::Chunked(ref b) => b.remaining(),
             BufKind::ChunkedEnd(ref b) => b.remaining(),
+            BufKind::Trailers(ref b) => b.remaining(),
         }
     }
 
@@ -236,6 +307,7 @@ where
             BufKind::Limited(ref b) => b.chunk(),
             BufKind::Chunked(ref b) => b.chunk(),
             BufKind::ChunkedEnd(ref b) => b.chunk(),
+            BufKind::Trailers(ref b) => b.chunk(),
         }
     }
 
@@ -246,6 +318,7 @@ where
             BufKind::Limited(ref mut b) => b.advance(cnt),
             BufKind::Chunked(ref mut b) => b.advance(cnt),
             BufKind::ChunkedEnd(ref mut b) => b.advance(cnt),
+            BufKind::Trailers(ref mut b) => b.advance(cnt),
         }
     }
 
@@ -256,6 +329,7 @@ where
             BufKind::Limited(ref b) => b.chunks_vectored(dst),
             BufKind::Chunked(ref b) => b.chunks_vectored(dst),
             BufKind::ChunkedEnd(ref b) => b.chunks_vectored(dst),
+            BufKind::Trailers(ref b) => b.chunks_vectored(dst),
         }
     }
 }
@@ -354,6 +428,7 @@ impl<B: Buf> From<Chain<Chain<ChunkSize, B>, StaticBuf>> for EncodedBuf<B> {
 #[cfg(test)]
 mod tests {
     use bytes::BufMut;
+    use http::{HeaderMap, HeaderValue};
 
     use super::super::io::Cursor;
     use super::Encoder;
@@ -428,4 +503,70 @@ mod tests {
         assert!(!encoder.is_eof());
         encoder.end::<()>().unwrap();
     }
+
+    #[test]
+    fn chunked_with_trailers() {
+        let mut encoder = Encoder::chunked();
+        let mut dst = Vec::new();
+
+        let msg1 = b"foo bar".as_ref();
+        let buf1 = encoder.encode(msg1);
+        dst.put(buf1);
+
+        let mut trailers = HeaderMap::new();
+        trailers.insert("chunky-trailer", HeaderValue::from_static("header data"));
+        let end = encoder
+            .encode_trailers::<Cursor<Vec<u8>>>(trailers, false)
+            .unwrap();
+        dst.put(end);
+
+        assert_eq!(
+            dst,
+            b"7\r\nfoo bar\r\n0\r\nchunky-trailer: header data\r\n\r\n".as_ref()
+        );
+    }
+
+    #[test]
+    fn chunked_with_trailers_title_case() {
+        let encoder = Encoder::chunked();
+        let mut dst = Vec::new();
+
+        let mut trailers = HeaderMap::new();
+        trailers.insert("chunky-trailer", HeaderValue::from_static("header data"));
+        let end = encoder
+            .encode_trailers::<Cursor<Vec<u8>>>(trailers, true)
+            .unwrap();
+        dst.put(end);
+
+        assert_eq!(dst, b"0\r\nChunky-Trailer: header data\r\n\r\n".as_ref());
+    }
+
+    #[test]
+    fn chunked_with_invalid_trailers() {
+        let encoder = Encoder::chunked();
+        let mut dst = Vec::new();
+
+        let mut trailers = HeaderMap::new();
+        trailers.insert("chunky-trailer", HeaderValue::from_static("header data"));
+        trailers.insert("content-length", HeaderValue::from_static("10"));
+        trailers.insert("transfer-encoding", HeaderValue::from_static("chunked"));
+        trailers.insert("host", HeaderValue::from_static("example.domain"));
+        trailers.insert("trailer", HeaderValue::from_static("chunky-trailer"));
+        trailers.insert("te", HeaderValue::from_static("trailers"));
+        trailers.insert("connection", HeaderValue::from_static("close"));
+        let end = encoder
+            .encode_trailers::<Cursor<Vec<u8>>>(trailers, false)
+            .unwrap();
+        dst.put(end);
+
+        assert_eq!(dst, b"0\r\nchunky-trailer: header data\r\n\r\n".as_ref());
+    }
+
+    #[test]
+    fn length_ignores_trailers() {
+        let encoder = Encoder::length(8);
+        assert!(encoder
+            .encode_trailers::<()>(HeaderMap::new(), false)
+            .is_none());
+    }
 }
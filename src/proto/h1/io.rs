@@ -42,6 +42,7 @@ pub(crate) struct Buffered<T, B> {
     read_blocked: bool,
     read_buf: BytesMut,
     read_buf_strategy: ReadStrategy,
+    read_once_per_poll: bool,
     write_buf: WriteBuf<B>,
 }
 
@@ -75,6 +76,7 @@ where
             read_blocked: false,
             read_buf: BytesMut::with_capacity(0),
             read_buf_strategy: ReadStrategy::default(),
+            read_once_per_poll: false,
             write_buf,
         }
     }
@@ -98,11 +100,31 @@ where
         self.write_buf.max_buf_size = max;
     }
 
+    #[cfg(feature = "server")]
+    pub(crate) fn set_read_buf_initial_capacity(&mut self, cap: usize) {
+        // `set_max_buf_size` replaces the strategy, so this must be called
+        // after it.
+        match self.read_buf_strategy {
+            ReadStrategy::Adaptive {
+                ref mut next, max, ..
+            } => {
+                *next = cmp::min(cmp::max(cap, INIT_BUFFER_SIZE), max);
+            }
+            #[cfg(feature = "client")]
+            ReadStrategy::Exact(_) => (),
+        }
+    }
+
     #[cfg(feature = "client")]
     pub(crate) fn set_read_buf_exact_size(&mut self, sz: usize) {
         self.read_buf_strategy = ReadStrategy::Exact(sz);
     }
 
+    #[cfg(feature = "client")]
+    pub(crate) fn set_read_once_per_poll(&mut self) {
+        self.read_once_per_poll = true;
+    }
+
     pub(crate) fn set_write_strategy_flatten(&mut self) {
         // this should always be called only at construction time,
         // so this assert is here to catch myself
@@ -121,8 +143,6 @@ where
         self.read_buf.as_ref()
     }
 
-    #[cfg(test)]
-    #[cfg(feature = "nightly")]
     pub(super) fn read_buf_mut(&mut self) -> &mut BytesMut {
         &mut self.read_buf
     }
@@ -180,6 +200,7 @@ where
     where
         S: Http1Transaction,
     {
+        let mut did_read = false;
         loop {
             match super::role::parse_headers::<S>(
                 &mut self.read_buf,
@@ -195,6 +216,18 @@ where
                     h1_header_read_timeout_running: parse_ctx.h1_header_read_timeout_running,
                     preserve_header_case: parse_ctx.preserve_header_case,
                     h09_responses: parse_ctx.h09_responses,
+                    #[cfg(feature = "server")]
+                    uri_validator: parse_ctx.uri_validator,
+                    #[cfg(feature = "server")]
+                    require_host_header: parse_ctx.require_host_header,
+                    #[cfg(feature = "server")]
+                    strict_expect: parse_ctx.strict_expect,
+                    #[cfg(feature = "server")]
+                    reject_connect: parse_ctx.reject_connect,
+                    #[cfg(feature = "server")]
+                    max_uri_len: parse_ctx.max_uri_len,
+                    #[cfg(feature = "server")]
+                    allow_obsolete_line_folding: parse_ctx.allow_obsolete_line_folding,
                     #[cfg(feature = "ffi")]
                     on_informational: parse_ctx.on_informational,
                     #[cfg(feature = "ffi")]
@@ -235,10 +268,17 @@ where
                     }
                 }
             }
+            if did_read && self.read_once_per_poll {
+                // Yield after each read, so that a message split across
+                // several reads needs as many polls to be parsed.
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
             if ready!(self.poll_read_from_io(cx)).map_err(crate::Error::new_io)? == 0 {
                 trace!("parse eof");
                 return Poll::Ready(Err(crate::Error::new_incomplete()));
             }
+            did_read = true;
         }
     }
 
@@ -280,6 +320,11 @@ where
         (self.io, self.read_buf.freeze())
     }
 
+    pub(crate) fn take_write_buf(&mut self) -> Bytes {
+        let len = self.write_buf.remaining();
+        self.write_buf.copy_to_bytes(len)
+    }
+
     pub(crate) fn io_mut(&mut self) -> &mut T {
         &mut self.io
     }
@@ -732,6 +777,12 @@ mod tests {
                 h1_header_read_timeout_running: &mut false,
                 preserve_header_case: false,
                 h09_responses: false,
+                uri_validator: None,
+                require_host_header: false,
+                strict_expect: false,
+                reject_connect: false,
+                max_uri_len: None,
+                allow_obsolete_line_folding: false,
                 #[cfg(feature = "ffi")]
                 on_informational: &mut None,
                 #[cfg(feature = "ffi")]
@@ -750,6 +801,69 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn parse_reads_once_per_poll() {
+        use crate::proto::h1::ClientTransaction;
+
+        let _ = pretty_env_logger::try_init();
+        let mock = Mock::new()
+            .read(b"HTTP/1.1 200 OK\r\n")
+            .read(b"Content-Length: 0\r\n\r\n")
+            .build();
+
+        let mut buffered = Buffered::<_, Cursor<Vec<u8>>>::new(mock);
+        buffered.set_read_once_per_poll();
+
+        let mut polls = 0;
+        let msg = futures_util::future::poll_fn(|cx| {
+            polls += 1;
+            let parse_ctx = ParseContext {
+                cached_headers: &mut None,
+                req_method: &mut None,
+                h1_parser_config: Default::default(),
+                h1_header_read_timeout: None,
+                h1_header_read_timeout_fut: &mut None,
+                h1_header_read_timeout_running: &mut false,
+                preserve_header_case: false,
+                h09_responses: false,
+                uri_validator: None,
+                require_host_header: false,
+                strict_expect: false,
+                reject_connect: false,
+                max_uri_len: None,
+                allow_obsolete_line_folding: false,
+                #[cfg(feature = "ffi")]
+                on_informational: &mut None,
+                #[cfg(feature = "ffi")]
+                raw_headers: false,
+            };
+            buffered.parse::<ClientTransaction>(cx, parse_ctx)
+        })
+        .await
+        .expect("parse");
+
+        assert_eq!(msg.head.subject, crate::StatusCode::OK);
+        assert_eq!(polls, 2, "one poll per read");
+    }
+
+    #[tokio::test]
+    async fn read_buf_initial_capacity() {
+        let _ = pretty_env_logger::try_init();
+        let mock = Mock::new().read(b"POST / HTTP/1.1\r\n").build();
+
+        let mut buffered = Buffered::<_, Cursor<Vec<u8>>>::new(mock);
+        buffered.set_read_buf_initial_capacity(64 * 1024);
+
+        futures_util::future::poll_fn(|cx| buffered.poll_read_from_io(cx))
+            .await
+            .expect("read");
+        assert!(
+            buffered.read_buf.capacity() >= 64 * 1024,
+            "first read uses the initial capacity: {}",
+            buffered.read_buf.capacity()
+        );
+    }
+
     #[test]
     fn read_strategy_adaptive_increments() {
         let mut strategy = ReadStrategy::default();
@@ -1,7 +1,13 @@
+#[cfg(feature = "server")]
+use std::fmt;
+#[cfg(feature = "server")]
+use std::sync::Arc;
 #[cfg(all(feature = "server", feature = "runtime"))]
 use std::{pin::Pin, time::Duration};
 
 use bytes::BytesMut;
+#[cfg(feature = "server")]
+use http::Uri;
 use http::{HeaderMap, Method};
 use httparse::ParserConfig;
 #[cfg(all(feature = "server", feature = "runtime"))]
@@ -22,6 +28,8 @@ mod decode;
 pub(crate) mod dispatch;
 mod encode;
 mod io;
+#[cfg(feature = "server")]
+mod proxy;
 mod role;
 
 
@@ -59,6 +67,13 @@ pub(crate) trait Http1Transaction {
     }
 
     fn update_date() {}
+
+    /// Whether sending a message with this subject switches the connection
+    /// to an upgraded protocol.
+    #[cfg(feature = "server")]
+    fn is_upgrade(_subject: &Self::Outgoing, _req_method: &Option<Method>) -> bool {
+        false
+    }
 }
 
 /// Result newtype for Http1Transaction::parse.
@@ -85,12 +100,50 @@ pub(crate) struct ParseContext<'a> {
     h1_header_read_timeout_running: &'a mut bool,
     preserve_header_case: bool,
     h09_responses: bool,
+    #[cfg(feature = "server")]
+    uri_validator: Option<&'a UriValidator>,
+    #[cfg(feature = "server")]
+    require_host_header: bool,
+    #[cfg(feature = "server")]
+    strict_expect: bool,
+    #[cfg(feature = "server")]
+    reject_connect: bool,
+    #[cfg(feature = "server")]
+    max_uri_len: Option<usize>,
+    #[cfg(feature = "server")]
+    allow_obsolete_line_folding: bool,
     #[cfg(feature = "ffi")]
     on_informational: &'a mut Option<crate::ffi::OnInformational>,
     #[cfg(feature = "ffi")]
     raw_headers: bool,
 }
 
+/// A user-supplied check of each incoming request-target.
+#[cfg(feature = "server")]
+#[derive(Clone)]
+pub(crate) struct UriValidator(Arc<dyn Fn(&Uri) -> bool + Send + Sync>);
+
+#[cfg(feature = "server")]
+impl UriValidator {
+    pub(crate) fn new<F>(f: F) -> UriValidator
+    where
+        F: Fn(&Uri) -> bool + Send + Sync + 'static,
+    {
+        UriValidator(Arc::new(f))
+    }
+
+    fn is_valid(&self, uri: &Uri) -> bool {
+        (self.0)(uri)
+    }
+}
+
+#[cfg(feature = "server")]
+impl fmt::Debug for UriValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UriValidator").finish()
+    }
+}
+
 /// Passed to Http1Transaction::encode
 pub(crate) struct Encode<'a, T> {
     head: &'a mut MessageHead<T>,
@@ -99,6 +152,10 @@ pub(crate) struct Encode<'a, T> {
     keep_alive: bool,
     req_method: &'a mut Option<Method>,
     title_case_headers: bool,
+    #[cfg(feature = "server")]
+    on_response: Option<&'a crate::proto::ResponseHook>,
+    #[cfg(feature = "server")]
+    http10_no_content_length: bool,
 }
 
 /// Extra flags that a request "wants", like expect-continue or upgrades.
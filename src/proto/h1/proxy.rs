@@ -0,0 +1,139 @@
+use std::net::{IpAddr, SocketAddr};
+
+use bytes::{Buf, BytesMut};
+use tracing::{debug, trace};
+
+use crate::error::Parse;
+use crate::ext::ProxyAddrs;
+
+const PREFIX: &[u8] = b"PROXY ";
+
+/// The longest header allowed by version 1 of the PROXY protocol,
+/// including the trailing CRLF.
+const MAX_HEADER_LEN: usize = 107;
+
+/// Tries to parse a PROXY protocol v1 header off the front of `buf`.
+///
+/// Returns `Ok(None)` if more bytes are needed. Once a full line has been
+/// parsed, it is removed from `buf`, and the announced addresses are
+/// returned, unless the proxy sent `UNKNOWN`.
+pub(super) fn parse(buf: &mut BytesMut) -> crate::Result<Option<Option<ProxyAddrs>>> {
+    let prefix_len = buf.len().min(PREFIX.len());
+    if buf[..prefix_len] != PREFIX[..prefix_len] {
+        debug!("connection did not start with a PROXY header");
+        return Err(Parse::ProxyHeader.into());
+    }
+
+    let end = match buf.windows(2).position(|w| w == b"\r\n") {
+        Some(pos) => pos,
+        None if buf.len() >= MAX_HEADER_LEN => {
+            debug!("PROXY header longer than {} bytes", MAX_HEADER_LEN);
+            return Err(Parse::ProxyHeader.into());
+        }
+        None => return Ok(None),
+    };
+    if end + 2 > MAX_HEADER_LEN {
+        debug!("PROXY header longer than {} bytes", MAX_HEADER_LEN);
+        return Err(Parse::ProxyHeader.into());
+    }
+
+    let addrs = parse_line(&buf[PREFIX.len()..end]).ok_or(Parse::ProxyHeader)?;
+    trace!("parsed PROXY header: {:?}", addrs);
+    buf.advance(end + 2);
+    Ok(Some(addrs))
+}
+
+fn parse_line(line: &[u8]) -> Option<Option<ProxyAddrs>> {
+    let line = std::str::from_utf8(line).ok()?;
+    let mut parts = line.split(' ');
+    let is_v4 = match parts.next()? {
+        "TCP4" => true,
+        "TCP6" => false,
+        // The rest of an UNKNOWN line must be ignored.
+        "UNKNOWN" => return Some(None),
+        _ => return None,
+    };
+
+    let src_ip = parts.next()?.parse::<IpAddr>().ok()?;
+    let dst_ip = parts.next()?.parse::<IpAddr>().ok()?;
+    let src_port = parse_port(parts.next()?)?;
+    let dst_port = parse_port(parts.next()?)?;
+    if parts.next().is_some() || src_ip.is_ipv4() != is_v4 || dst_ip.is_ipv4() != is_v4 {
+        return None;
+    }
+
+    Some(Some(ProxyAddrs::new(
+        SocketAddr::new(src_ip, src_port),
+        SocketAddr::new(dst_ip, dst_port),
+    )))
+}
+
+fn parse_port(s: &str) -> Option<u16> {
+    // Ports are plain decimal numbers, without a sign or leading zeros.
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) || (s.len() > 1 && s.starts_with('0'))
+    {
+        return None;
+    }
+    s.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tcp4() {
+        let mut buf = BytesMut::from("PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nGET /");
+        let addrs = parse(&mut buf).unwrap().unwrap().unwrap();
+        assert_eq!(addrs.source(), "192.168.0.1:56324".parse().unwrap());
+        assert_eq!(addrs.destination(), "192.168.0.11:443".parse().unwrap());
+        assert_eq!(buf, "GET /");
+    }
+
+    #[test]
+    fn parse_tcp6() {
+        let mut buf = BytesMut::from("PROXY TCP6 2001:db8::1 ::1 4000 80\r\n");
+        let addrs = parse(&mut buf).unwrap().unwrap().unwrap();
+        assert_eq!(addrs.source(), "[2001:db8::1]:4000".parse().unwrap());
+        assert_eq!(addrs.destination(), "[::1]:80".parse().unwrap());
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn parse_unknown() {
+        let mut buf = BytesMut::from("PROXY UNKNOWN ffff::1 ffff::2 1 2\r\nGET /");
+        assert_eq!(parse(&mut buf).unwrap(), Some(None));
+        assert_eq!(buf, "GET /");
+    }
+
+    #[test]
+    fn parse_partial() {
+        let mut buf = BytesMut::from("PRO");
+        assert_eq!(parse(&mut buf).unwrap(), None);
+
+        let mut buf = BytesMut::from("PROXY TCP4 192.168.0.1 192.168.0.11 56324");
+        assert_eq!(parse(&mut buf).unwrap(), None);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for line in &[
+            "GET / HTTP/1.1\r\n",
+            "PROXY TCP5 192.168.0.1 192.168.0.11 56324 443\r\n",
+            "PROXY TCP4 192.168.0.1 192.168.0.11 56324\r\n",
+            "PROXY TCP4 192.168.0.1 192.168.0.11 56324 443 80\r\n",
+            "PROXY TCP4 192.168.0.1  192.168.0.11 56324 443\r\n",
+            "PROXY TCP4 ::1 192.168.0.11 56324 443\r\n",
+            "PROXY TCP6 ::1 ::2 65536 443\r\n",
+            "PROXY TCP6 ::1 ::2 +80 443\r\n",
+            "PROXY TCP6 ::1 ::2 080 443\r\n",
+        ] {
+            let mut buf = BytesMut::from(*line);
+            parse(&mut buf).expect_err(line);
+        }
+
+        let mut buf = BytesMut::from("PROXY UNKNOWN ");
+        buf.extend_from_slice(&[b'x'; MAX_HEADER_LEN]);
+        parse(&mut buf).expect_err("too long");
+    }
+}
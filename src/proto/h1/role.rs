@@ -3,7 +3,6 @@ use std::mem::MaybeUninit;
 
 #[cfg(all(feature = "server", feature = "runtime"))]
 use tokio::time::Instant;
-#[cfg(any(test, feature = "server", feature = "ffi"))]
 use bytes::Bytes;
 use bytes::BytesMut;
 #[cfg(feature = "server")]
@@ -17,6 +16,8 @@ use crate::body::DecodedLength;
 use crate::common::date;
 use crate::error::Parse;
 use crate::ext::HeaderCaseMap;
+#[cfg(feature = "client")]
+use crate::ext::ReasonPhrase;
 use crate::headers;
 use crate::proto::h1::{
     Encode, Encoder, Http1Transaction, ParseContext, ParseResult, ParsedMessage,
@@ -122,6 +123,11 @@ impl Http1Transaction for Server {
     type Outgoing = StatusCode;
     const LOG: &'static str = "{role=server}";
 
+    fn is_upgrade(status: &StatusCode, req_method: &Option<Method>) -> bool {
+        *status == StatusCode::SWITCHING_PROTOCOLS
+            || (*req_method == Some(Method::CONNECT) && status.is_success())
+    }
+
     fn parse(buf: &mut BytesMut, ctx: ParseContext<'_>) -> ParseResult<RequestLine> {
         debug_assert!(!buf.is_empty(), "parse called with empty buf");
 
@@ -140,6 +146,9 @@ impl Http1Transaction for Server {
             // SAFETY: We can go safely from MaybeUninit array to array of MaybeUninit
             MaybeUninit::uninit().assume_init()
         };
+        if ctx.allow_obsolete_line_folding {
+            Server::unfold_obsolete_line_folding(buf);
+        }
         {
             /* SAFETY: it is safe to go from MaybeUninit array to array of MaybeUninit */
             let mut headers: [MaybeUninit<httparse::Header<'_>>; MAX_HEADERS] =
@@ -147,18 +156,34 @@ impl Http1Transaction for Server {
             trace!(bytes = buf.len(), "Request.parse");
             let mut req = httparse::Request::new(&mut []);
             let bytes = buf.as_ref();
-            match req.parse_with_uninit_headers(bytes, &mut headers) {
+            match ctx.h1_parser_config.parse_request_with_uninit_headers(
+                &mut req,
+                bytes,
+                &mut headers,
+            ) {
                 Ok(httparse::Status::Complete(parsed_len)) => {
                     trace!("Request.parse Complete({})", parsed_len);
                     len = parsed_len;
                     let uri = req.path.unwrap();
-                    if uri.len() > MAX_URI_LEN {
+                    if uri.len() > MAX_URI_LEN
+                        || matches!(ctx.max_uri_len, Some(max) if uri.len() > max)
+                    {
                         return Err(Parse::UriTooLong);
                     }
                     subject = RequestLine(
                         Method::from_bytes(req.method.unwrap().as_bytes())?,
                         uri.parse()?,
                     );
+                    if ctx.reject_connect && subject.0 == Method::CONNECT {
+                        debug!("CONNECT request rejected");
+                        return Err(Parse::MethodNotAllowed);
+                    }
+                    if let Some(validator) = ctx.uri_validator {
+                        if !validator.is_valid(&subject.1) {
+                            debug!("request-target rejected by uri validator");
+                            return Err(Parse::Uri);
+                        }
+                    }
                     version = if req.version.unwrap() == 1 {
                         keep_alive = true;
                         is_http_11 = true;
@@ -277,6 +302,10 @@ impl Http1Transaction for Server {
                     // Comparison of expectation values is case-insensitive for unquoted tokens
                     // (including the 100-continue token)
                     expect_continue = value.as_bytes().eq_ignore_ascii_case(b"100-continue");
+                    if ctx.strict_expect && !expect_continue {
+                        debug!("request with unsupported expectation: {:?}", value);
+                        return Err(Parse::expect_unsupported());
+                    }
                 }
                 header::UPGRADE => {
                     // Upgrades are only allowed with HTTP/1.1
@@ -298,6 +327,14 @@ impl Http1Transaction for Server {
             return Err(Parse::transfer_encoding_invalid());
         }
 
+        // https://tools.ietf.org/html/rfc7230#section-5.4
+        // A server MUST respond with a 400 (Bad Request) status code to any
+        // HTTP/1.1 request message that lacks a Host header field.
+        if ctx.require_host_header && is_http_11 && !headers.contains_key(header::HOST) {
+            debug!("HTTP/1.1 request without a host header, bad request");
+            return Err(Parse::host_missing());
+        }
+
         let mut extensions = http::Extensions::default();
 
         if let Some(header_case_map) = header_case_map {
@@ -328,6 +365,10 @@ impl Http1Transaction for Server {
             msg.req_method
         );
 
+        if let Some(hook) = msg.on_response {
+            hook.call_head(msg.head);
+        }
+
         let mut wrote_len = false;
 
         // hyper currently doesn't support returning 1xx status codes as a Response
@@ -411,20 +452,31 @@ impl Http1Transaction for Server {
     }
 
     fn on_error(err: &crate::Error) -> Option<MessageHead<Self::Outgoing>> {
-        use crate::error::Kind;
+        use crate::error::{Header, Kind};
         let status = match *err.kind() {
+            Kind::Parse(Parse::Header(Header::ExpectUnsupported)) => StatusCode::EXPECTATION_FAILED,
             Kind::Parse(Parse::Method)
             | Kind::Parse(Parse::Header(_))
             | Kind::Parse(Parse::Uri)
             | Kind::Parse(Parse::Version) => StatusCode::BAD_REQUEST,
             Kind::Parse(Parse::TooLarge) => StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
             Kind::Parse(Parse::UriTooLong) => StatusCode::URI_TOO_LONG,
+            Kind::Parse(Parse::MethodNotAllowed) => StatusCode::METHOD_NOT_ALLOWED,
+            Kind::BodyTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
             _ => return None,
         };
 
         debug!("sending automatic response ({}) for parse error", status);
         let mut msg = MessageHead::default();
         msg.subject = status;
+        if status == StatusCode::METHOD_NOT_ALLOWED {
+            // Only CONNECT is rejected this way, every other method is
+            // handed to the service.
+            msg.headers.insert(
+                header::ALLOW,
+                HeaderValue::from_static("GET, HEAD, POST, PUT, DELETE, OPTIONS, TRACE, PATCH"),
+            );
+        }
         Some(msg)
     }
 
@@ -468,6 +520,40 @@ impl Server {
         }
     }
 
+    /// Replaces each obs-fold in the request head with spaces, so that a
+    /// continuation line becomes part of the previous header value.
+    ///
+    /// The replacement is done in place, so it's fine to run again over the
+    /// same bytes if the head wasn't complete yet.
+    fn unfold_obsolete_line_folding(buf: &mut [u8]) {
+        // The request line can't be folded, so start after it.
+        let headers_start = match buf.iter().position(|&b| b == b'\n') {
+            Some(pos) => pos + 1,
+            None => return,
+        };
+        let mut line_start = headers_start;
+        for i in headers_start..buf.len() {
+            if buf[i] != b'\n' {
+                continue;
+            }
+            let line = &buf[line_start..i];
+            if line.is_empty() || line == b"\r" {
+                // end of the head, don't touch the body
+                return;
+            }
+            match buf.get(i + 1) {
+                Some(b' ') | Some(b'\t') => {
+                    trace!("unfolding obsolete line folding");
+                    buf[i] = b' ';
+                    if buf[i - 1] == b'\r' {
+                        buf[i - 1] = b' ';
+                    }
+                }
+                _ => line_start = i + 1,
+            }
+        }
+    }
+
     fn encode_headers_with_lower_case(
         msg: Encode<'_, StatusCode>,
         dst: &mut Vec<u8>,
@@ -604,6 +690,13 @@ impl Server {
         let mut must_write_chunked = false;
         let mut prev_con_len = None;
 
+        // If configured, HTTP/1.0 responses are framed by closing the
+        // connection, without a content-length.
+        let close_delimited = msg.http10_no_content_length && msg.head.version == Version::HTTP_10;
+        if close_delimited {
+            is_last = true;
+        }
+
         macro_rules! handle_is_name_written {
             () => {{
                 if is_name_written {
@@ -632,6 +725,9 @@ impl Server {
             let name = cur_name.as_ref().expect("current header name");
             match *name {
                 header::CONTENT_LENGTH => {
+                    if close_delimited {
+                        continue 'headers;
+                    }
                     if wrote_len && !is_name_written {
                         warn!("unexpected content-length found, canceling");
                         rewind(dst);
@@ -805,6 +901,8 @@ impl Server {
 
         if !wrote_len {
             encoder = match msg.body {
+                Some(_) if close_delimited => Encoder::close_delimited(),
+                None if close_delimited => Encoder::length(0),
                 Some(BodyLength::Unknown) => {
                     if msg.head.version == Version::HTTP_10
                         || !Server::can_chunked(msg.req_method, msg.head.subject)
@@ -918,9 +1016,6 @@ impl Http1Transaction for Client {
                         trace!("Response.parse Complete({})", len);
                         let status = StatusCode::from_u16(res.code.unwrap())?;
 
-                        #[cfg(not(feature = "ffi"))]
-                        let reason = ();
-                        #[cfg(feature = "ffi")]
                         let reason = {
                             let reason = res.reason.unwrap();
                             // Only save the reason phrase if it isnt the canonical reason
@@ -944,9 +1039,6 @@ impl Http1Transaction for Client {
                     Err(httparse::Error::Version) if ctx.h09_responses => {
                         trace!("Response.parse accepted HTTP/0.9 response");
 
-                        #[cfg(not(feature = "ffi"))]
-                        let reason = ();
-                        #[cfg(feature = "ffi")]
                         let reason = None;
 
                         (0, StatusCode::OK, reason, Version::HTTP_09, 0)
@@ -998,12 +1090,9 @@ impl Http1Transaction for Client {
                 extensions.insert(header_case_map);
             }
 
-            #[cfg(feature = "ffi")]
             if let Some(reason) = reason {
-                extensions.insert(crate::ffi::ReasonPhrase(reason));
+                extensions.insert(ReasonPhrase::from_bytes_unchecked(reason));
             }
-            #[cfg(not(feature = "ffi"))]
-            drop(reason);
 
             #[cfg(feature = "ffi")]
             if ctx.raw_headers {
@@ -1369,7 +1458,7 @@ fn title_case(dst: &mut Vec<u8>, name: &[u8]) {
     }
 }
 
-fn write_headers_title_case(headers: &HeaderMap, dst: &mut Vec<u8>) {
+pub(super) fn write_headers_title_case(headers: &HeaderMap, dst: &mut Vec<u8>) {
     for (name, value) in headers {
         title_case(dst, name.as_str().as_bytes());
         extend(dst, b": ");
@@ -1378,7 +1467,7 @@ fn write_headers_title_case(headers: &HeaderMap, dst: &mut Vec<u8>) {
     }
 }
 
-fn write_headers(headers: &HeaderMap, dst: &mut Vec<u8>) {
+pub(super) fn write_headers(headers: &HeaderMap, dst: &mut Vec<u8>) {
     for (name, value) in headers {
         extend(dst, name.as_str().as_bytes());
         extend(dst, b": ");
@@ -1465,6 +1554,12 @@ mod tests {
                 h1_header_read_timeout_running: &mut false,
                 preserve_header_case: false,
                 h09_responses: false,
+                uri_validator: None,
+                require_host_header: false,
+                strict_expect: false,
+                reject_connect: false,
+                max_uri_len: None,
+                allow_obsolete_line_folding: false,
                 #[cfg(feature = "ffi")]
                 on_informational: &mut None,
                 #[cfg(feature = "ffi")]
@@ -1495,6 +1590,12 @@ mod tests {
             h1_header_read_timeout_running: &mut false,
             preserve_header_case: false,
             h09_responses: false,
+            uri_validator: None,
+            require_host_header: false,
+            strict_expect: false,
+            reject_connect: false,
+            max_uri_len: None,
+            allow_obsolete_line_folding: false,
             #[cfg(feature = "ffi")]
             on_informational: &mut None,
             #[cfg(feature = "ffi")]
@@ -1520,6 +1621,12 @@ mod tests {
             h1_header_read_timeout_running: &mut false,
             preserve_header_case: false,
             h09_responses: false,
+            uri_validator: None,
+            require_host_header: false,
+            strict_expect: false,
+            reject_connect: false,
+            max_uri_len: None,
+            allow_obsolete_line_folding: false,
             #[cfg(feature = "ffi")]
             on_informational: &mut None,
             #[cfg(feature = "ffi")]
@@ -1543,6 +1650,12 @@ mod tests {
             h1_header_read_timeout_running: &mut false,
             preserve_header_case: false,
             h09_responses: true,
+            uri_validator: None,
+            require_host_header: false,
+            strict_expect: false,
+            reject_connect: false,
+            max_uri_len: None,
+            allow_obsolete_line_folding: false,
             #[cfg(feature = "ffi")]
             on_informational: &mut None,
             #[cfg(feature = "ffi")]
@@ -1568,6 +1681,12 @@ mod tests {
             h1_header_read_timeout_running: &mut false,
             preserve_header_case: false,
             h09_responses: false,
+            uri_validator: None,
+            require_host_header: false,
+            strict_expect: false,
+            reject_connect: false,
+            max_uri_len: None,
+            allow_obsolete_line_folding: false,
             #[cfg(feature = "ffi")]
             on_informational: &mut None,
             #[cfg(feature = "ffi")]
@@ -1597,6 +1716,12 @@ mod tests {
             h1_header_read_timeout_running: &mut false,
             preserve_header_case: false,
             h09_responses: false,
+            uri_validator: None,
+            require_host_header: false,
+            strict_expect: false,
+            reject_connect: false,
+            max_uri_len: None,
+            allow_obsolete_line_folding: false,
             #[cfg(feature = "ffi")]
             on_informational: &mut None,
             #[cfg(feature = "ffi")]
@@ -1623,6 +1748,12 @@ mod tests {
             h1_header_read_timeout_running: &mut false,
             preserve_header_case: false,
             h09_responses: false,
+            uri_validator: None,
+            require_host_header: false,
+            strict_expect: false,
+            reject_connect: false,
+            max_uri_len: None,
+            allow_obsolete_line_folding: false,
             #[cfg(feature = "ffi")]
             on_informational: &mut None,
             #[cfg(feature = "ffi")]
@@ -1644,6 +1775,12 @@ mod tests {
             h1_header_read_timeout_running: &mut false,
             preserve_header_case: true,
             h09_responses: false,
+            uri_validator: None,
+            require_host_header: false,
+            strict_expect: false,
+            reject_connect: false,
+            max_uri_len: None,
+            allow_obsolete_line_folding: false,
             #[cfg(feature = "ffi")]
             on_informational: &mut None,
             #[cfg(feature = "ffi")]
@@ -1686,6 +1823,12 @@ mod tests {
                     h1_header_read_timeout_running: &mut false,
                     preserve_header_case: false,
                     h09_responses: false,
+                    uri_validator: None,
+                    require_host_header: false,
+                    strict_expect: false,
+                    reject_connect: false,
+                    max_uri_len: None,
+                    allow_obsolete_line_folding: false,
                     #[cfg(feature = "ffi")]
                     on_informational: &mut None,
                     #[cfg(feature = "ffi")]
@@ -1709,6 +1852,12 @@ mod tests {
                     h1_header_read_timeout_running: &mut false,
                     preserve_header_case: false,
                     h09_responses: false,
+                    uri_validator: None,
+                    require_host_header: false,
+                    strict_expect: false,
+                    reject_connect: false,
+                    max_uri_len: None,
+                    allow_obsolete_line_folding: false,
                     #[cfg(feature = "ffi")]
                     on_informational: &mut None,
                     #[cfg(feature = "ffi")]
@@ -1941,6 +2090,12 @@ mod tests {
                     h1_header_read_timeout_running: &mut false,
                     preserve_header_case: false,
                     h09_responses: false,
+                    uri_validator: None,
+                    require_host_header: false,
+                    strict_expect: false,
+                    reject_connect: false,
+                    max_uri_len: None,
+                    allow_obsolete_line_folding: false,
                     #[cfg(feature = "ffi")]
                     on_informational: &mut None,
                     #[cfg(feature = "ffi")]
@@ -1964,6 +2119,12 @@ mod tests {
                     h1_header_read_timeout_running: &mut false,
                     preserve_header_case: false,
                     h09_responses: false,
+                    uri_validator: None,
+                    require_host_header: false,
+                    strict_expect: false,
+                    reject_connect: false,
+                    max_uri_len: None,
+                    allow_obsolete_line_folding: false,
                     #[cfg(feature = "ffi")]
                     on_informational: &mut None,
                     #[cfg(feature = "ffi")]
@@ -1987,6 +2148,12 @@ mod tests {
                     h1_header_read_timeout_running: &mut false,
                     preserve_header_case: false,
                     h09_responses: false,
+                    uri_validator: None,
+                    require_host_header: false,
+                    strict_expect: false,
+                    reject_connect: false,
+                    max_uri_len: None,
+                    allow_obsolete_line_folding: false,
                     #[cfg(feature = "ffi")]
                     on_informational: &mut None,
                     #[cfg(feature = "ffi")]
@@ -2276,6 +2443,8 @@ mod tests {
                 keep_alive: true,
                 req_method: &mut None,
                 title_case_headers: true,
+                on_response: None,
+                http10_no_content_length: false,
             },
             &mut vec,
         )
@@ -2307,6 +2476,8 @@ mod tests {
                 keep_alive: true,
                 req_method: &mut None,
                 title_case_headers: false,
+                on_response: None,
+                http10_no_content_length: false,
             },
             &mut vec,
         )
@@ -2341,6 +2512,8 @@ mod tests {
                 keep_alive: true,
                 req_method: &mut None,
                 title_case_headers: true,
+                on_response: None,
+                http10_no_content_length: false,
             },
             &mut vec,
         )
@@ -2365,6 +2538,8 @@ mod tests {
                 keep_alive: true,
                 req_method: &mut Some(Method::CONNECT),
                 title_case_headers: false,
+                on_response: None,
+                http10_no_content_length: false,
             },
             &mut vec,
         )
@@ -2394,6 +2569,8 @@ mod tests {
                 keep_alive: true,
                 req_method: &mut None,
                 title_case_headers: true,
+                on_response: None,
+                http10_no_content_length: false,
             },
             &mut vec,
         )
@@ -2428,6 +2605,8 @@ mod tests {
                 keep_alive: true,
                 req_method: &mut None,
                 title_case_headers: false,
+                on_response: None,
+                http10_no_content_length: false,
             },
             &mut vec,
         )
@@ -2462,6 +2641,8 @@ mod tests {
                 keep_alive: true,
                 req_method: &mut None,
                 title_case_headers: true,
+                on_response: None,
+                http10_no_content_length: false,
             },
             &mut vec,
         )
@@ -2487,6 +2668,12 @@ mod tests {
                 h1_header_read_timeout_running: &mut false,
                 preserve_header_case: false,
                 h09_responses: false,
+                uri_validator: None,
+                require_host_header: false,
+                strict_expect: false,
+                reject_connect: false,
+                max_uri_len: None,
+                allow_obsolete_line_folding: false,
                 #[cfg(feature = "ffi")]
                 on_informational: &mut None,
                 #[cfg(feature = "ffi")]
@@ -2574,6 +2761,12 @@ mod tests {
                     h1_header_read_timeout_running: &mut false,
                     preserve_header_case: false,
                     h09_responses: false,
+                    uri_validator: None,
+                    require_host_header: false,
+                    strict_expect: false,
+                    reject_connect: false,
+                    max_uri_len: None,
+                    allow_obsolete_line_folding: false,
                     #[cfg(feature = "ffi")]
                     on_informational: &mut None,
                     #[cfg(feature = "ffi")]
@@ -2617,6 +2810,12 @@ mod tests {
                     h1_header_read_timeout_running: &mut false,
                     preserve_header_case: false,
                     h09_responses: false,
+                    uri_validator: None,
+                    require_host_header: false,
+                    strict_expect: false,
+                    reject_connect: false,
+                    max_uri_len: None,
+                    allow_obsolete_line_folding: false,
                     #[cfg(feature = "ffi")]
                     on_informational: &mut None,
                     #[cfg(feature = "ffi")]
@@ -2663,6 +2862,8 @@ mod tests {
                     keep_alive: true,
                     req_method: &mut Some(Method::GET),
                     title_case_headers: false,
+                    on_response: None,
+                    http10_no_content_length: false,
                 },
                 &mut vec,
             )
@@ -2691,6 +2892,8 @@ mod tests {
                     keep_alive: true,
                     req_method: &mut Some(Method::GET),
                     title_case_headers: false,
+                    on_response: None,
+                    http10_no_content_length: false,
                 },
                 &mut vec,
             )
@@ -1,12 +1,14 @@
 use std::error::Error as StdError;
+use std::io::Cursor;
 use std::marker::Unpin;
+use std::sync::Arc;
 #[cfg(feature = "runtime")]
 use std::time::Duration;
 
-use bytes::Bytes;
+use bytes::{Buf, Bytes};
 use h2::server::{Connection, Handshake, SendResponse};
 use h2::{Reason, RecvStream};
-use http::{Method, Request};
+use http::{HeaderMap, Method, Request};
 use pin_project_lite::pin_project;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tracing::{debug, trace, warn};
@@ -15,10 +17,11 @@ use super::{ping, PipeToSendStream, SendBuf};
 use crate::body::HttpBody;
 use crate::common::exec::ConnStreamExec;
 use crate::common::{date, task, Future, Pin, Poll};
+use crate::ext::{Http2StreamId, PushReceiver, PushSender};
 use crate::headers;
 use crate::proto::h2::ping::Recorder;
 use crate::proto::h2::{H2Upgraded, UpgradedSendStream};
-use crate::proto::Dispatched;
+use crate::proto::{Dispatched, ResponseHook};
 use crate::service::HttpService;
 
 use crate::upgrade::{OnUpgrade, Pending, Upgraded};
@@ -33,6 +36,7 @@ use crate::{Body, Response};
 const DEFAULT_CONN_WINDOW: u32 = 1024 * 1024; // 1mb
 const DEFAULT_STREAM_WINDOW: u32 = 1024 * 1024; // 1mb
 const DEFAULT_MAX_FRAME_SIZE: u32 = 1024 * 16; // 16kb
+const DEFAULT_MAX_HEADER_LIST_SIZE: u32 = 16 << 20; // 16mb
 const DEFAULT_MAX_SEND_BUF_SIZE: usize = 1024 * 400; // 400kb
 
 #[derive(Clone, Debug)]
@@ -41,12 +45,15 @@ pub(crate) struct Config {
     pub(crate) initial_conn_window_size: u32,
     pub(crate) initial_stream_window_size: u32,
     pub(crate) max_frame_size: u32,
+    pub(crate) max_header_list_size: u32,
     pub(crate) max_concurrent_streams: Option<u32>,
     #[cfg(feature = "runtime")]
     pub(crate) keep_alive_interval: Option<Duration>,
     #[cfg(feature = "runtime")]
     pub(crate) keep_alive_timeout: Duration,
     pub(crate) max_send_buffer_size: usize,
+    pub(crate) on_response: Option<ResponseHook>,
+    pub(crate) default_headers: HeaderMap,
 }
 
 impl Default for Config {
@@ -56,12 +63,15 @@ impl Default for Config {
             initial_conn_window_size: DEFAULT_CONN_WINDOW,
             initial_stream_window_size: DEFAULT_STREAM_WINDOW,
             max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            max_header_list_size: DEFAULT_MAX_HEADER_LIST_SIZE,
             max_concurrent_streams: None,
             #[cfg(feature = "runtime")]
             keep_alive_interval: None,
             #[cfg(feature = "runtime")]
             keep_alive_timeout: Duration::from_secs(20),
             max_send_buffer_size: DEFAULT_MAX_SEND_BUF_SIZE,
+            on_response: None,
+            default_headers: HeaderMap::new(),
         }
     }
 }
@@ -85,6 +95,8 @@ where
     Handshaking {
         ping_config: ping::Config,
         hs: Handshake<T, SendBuf<B::Data>>,
+        on_response: Option<ResponseHook>,
+        default_headers: Option<Arc<HeaderMap>>,
     },
     Serving(Serving<T, B>),
     Closed,
@@ -97,6 +109,8 @@ where
     ping: Option<(ping::Recorder, ping::Ponger)>,
     conn: Connection<T, SendBuf<B::Data>>,
     closing: Option<crate::Error>,
+    on_response: Option<ResponseHook>,
+    default_headers: Option<Arc<HeaderMap>>,
 }
 
 impl<T, S, B, E> Server<T, S, B, E>
@@ -113,6 +127,7 @@ where
             .initial_window_size(config.initial_stream_window_size)
             .initial_connection_window_size(config.initial_conn_window_size)
             .max_frame_size(config.max_frame_size)
+            .max_header_list_size(config.max_header_list_size)
             .max_send_buffer_size(config.max_send_buffer_size);
         if let Some(max) = config.max_concurrent_streams {
             builder.max_concurrent_streams(max);
@@ -142,6 +157,12 @@ where
             state: State::Handshaking {
                 ping_config,
                 hs: handshake,
+                on_response: config.on_response.clone(),
+                default_headers: if config.default_headers.is_empty() {
+                    None
+                } else {
+                    Some(Arc::new(config.default_headers.clone()))
+                },
             },
             service,
         }
@@ -184,6 +205,8 @@ where
                 State::Handshaking {
                     ref mut hs,
                     ref ping_config,
+                    ref mut on_response,
+                    ref mut default_headers,
                 } => {
                     let mut conn = ready!(Pin::new(hs).poll(cx).map_err(crate::Error::new_h2))?;
                     let ping = if ping_config.is_enabled() {
@@ -196,6 +219,8 @@ where
                         ping,
                         conn,
                         closing: None,
+                        on_response: on_response.take(),
+                        default_headers: default_headers.take(),
                     })
                 }
                 State::Serving(ref mut srv) => {
@@ -280,6 +305,16 @@ where
 
                         let is_connect = req.method() == Method::CONNECT;
                         let (mut parts, stream) = req.into_parts();
+                        parts
+                            .extensions
+                            .insert(Http2StreamId::new(respond.stream_id().as_u32()));
+                        let pushes = if !is_connect {
+                            let (push_tx, push_rx) = PushSender::channel();
+                            parts.extensions.insert(push_tx);
+                            Some(push_rx)
+                        } else {
+                            None
+                        };
                         let (req, connect_parts) = if !is_connect {
                             (
                                 Request::from_parts(
@@ -307,7 +342,14 @@ where
                             )
                         };
 
-                        let fut = H2Stream::new(service.call(req), connect_parts, respond);
+                        let fut = H2Stream::new(
+                            service.call(req),
+                            connect_parts,
+                            pushes,
+                            respond,
+                            self.on_response.clone(),
+                            self.default_headers.clone(),
+                        );
                         exec.execute_h2stream(fut);
                     }
                     Some(Err(e)) => {
@@ -361,6 +403,9 @@ pin_project! {
         B: HttpBody,
     {
         reply: SendResponse<SendBuf<B::Data>>,
+        pushes: Option<PushReceiver>,
+        on_response: Option<ResponseHook>,
+        default_headers: Option<Arc<HeaderMap>>,
         #[pin]
         state: H2StreamState<F, B>,
     }
@@ -397,10 +442,16 @@ where
     fn new(
         fut: F,
         connect_parts: Option<ConnectParts>,
+        pushes: Option<PushReceiver>,
         respond: SendResponse<SendBuf<B::Data>>,
+        on_response: Option<ResponseHook>,
+        default_headers: Option<Arc<HeaderMap>>,
     ) -> H2Stream<F, B> {
         H2Stream {
             reply: respond,
+            pushes,
+            on_response,
+            default_headers,
             state: H2StreamState::Service { fut, connect_parts },
         }
     }
@@ -435,7 +486,15 @@ where
                     fut: h,
                     connect_parts,
                 } => {
-                    let res = match h.poll(cx) {
+                    let res = h.poll(cx);
+                    // Pushes made by the service must be promised before
+                    // the response is sent.
+                    if let Some(pushes) = me.pushes {
+                        for (req, res) in pushes.poll_take(cx) {
+                            push(me.reply, req, res);
+                        }
+                    }
+                    let res = match res {
                         Poll::Ready(Ok(r)) => r,
                         Poll::Pending => {
                             // Response is not yet ready, so we want to check if the client has sent a
@@ -456,8 +515,21 @@ where
                         }
                     };
 
+                    // Any later push is refused.
+                    *me.pushes = None;
+
                     let (head, body) = res.into_parts();
                     let mut res = ::http::Response::from_parts(head, ());
+                    if let Some(headers) = me.default_headers {
+                        for (name, value) in headers.iter() {
+                            res.headers_mut()
+                                .entry(name)
+                                .or_insert_with(|| value.clone());
+                        }
+                    }
+                    if let Some(hook) = me.on_response {
+                        hook.call(&mut res);
+                    }
                     super::strip_connection_headers(res.headers_mut(), false);
 
                     // set Date header if it isn't already set...
@@ -513,6 +585,36 @@ where
     }
 }
 
+fn push<B: Buf>(reply: &mut SendResponse<SendBuf<B>>, req: Request<()>, res: Response<Bytes>) {
+    let mut pushed = match reply.push_request(req) {
+        Ok(pushed) => pushed,
+        Err(e) => {
+            debug!("push promise error: {}", e);
+            return;
+        }
+    };
+
+    let (mut head, body) = res.into_parts();
+    super::strip_connection_headers(&mut head.headers, false);
+    head.headers
+        .entry(::http::header::DATE)
+        .or_insert_with(date::update_and_header_value);
+    headers::set_content_length_if_missing(&mut head.headers, body.len() as u64);
+
+    let res = Response::from_parts(head, ());
+    let result = if body.is_empty() {
+        pushed.send_response(res, true).map(|_| ())
+    } else {
+        pushed.send_response(res, false).and_then(|mut tx| {
+            let buf = SendBuf::Cursor(Cursor::new(Box::from(&body[..])));
+            tx.send_data(buf, true)
+        })
+    };
+    if let Err(e) = result {
+        debug!("pushed response error: {}", e);
+    }
+}
+
 impl<F, B, E> Future for H2Stream<F, B>
 where
     F: Future<Output = Result<Response<B>, E>>,
@@ -69,3 +69,46 @@ impl MessageHead<http::StatusCode> {
         res
     }
 }
+
+/// A user-supplied hook run on each response head before it is written.
+#[cfg(feature = "server")]
+#[derive(Clone)]
+pub(crate) struct ResponseHook(std::sync::Arc<ResponseHookFn>);
+
+#[cfg(feature = "server")]
+type ResponseHookFn = dyn Fn(&mut http::Response<()>) + Send + Sync;
+
+#[cfg(feature = "server")]
+impl ResponseHook {
+    pub(crate) fn new<F>(f: F) -> ResponseHook
+    where
+        F: Fn(&mut http::Response<()>) + Send + Sync + 'static,
+    {
+        ResponseHook(std::sync::Arc::new(f))
+    }
+
+    #[cfg(feature = "http2")]
+    pub(crate) fn call(&self, res: &mut http::Response<()>) {
+        (self.0)(res)
+    }
+
+    #[cfg(feature = "http1")]
+    pub(crate) fn call_head(&self, head: &mut MessageHead<http::StatusCode>) {
+        let mut res = std::mem::take(head).into_response(());
+        (self.0)(&mut res);
+        let (parts, ()) = res.into_parts();
+        *head = MessageHead {
+            version: parts.version,
+            subject: parts.status,
+            headers: parts.headers,
+            extensions: parts.extensions,
+        };
+    }
+}
+
+#[cfg(feature = "server")]
+impl std::fmt::Debug for ResponseHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResponseHook").finish()
+    }
+}
@@ -66,6 +66,8 @@ cfg_feature! {
     use std::fmt;
 
     use bytes::Bytes;
+    use http::header::{HeaderName, HeaderValue};
+    use http::HeaderMap;
     use pin_project_lite::pin_project;
     use tokio::io::{AsyncRead, AsyncWrite};
     use tracing::trace;
@@ -102,14 +104,35 @@ pub struct Http<E = Exec> {
     h1_keep_alive: bool,
     h1_title_case_headers: bool,
     h1_preserve_header_case: bool,
+    h1_require_host_header: bool,
+    h1_strict_expect: bool,
+    h1_allow_connect: bool,
+    h1_0_no_content_length: bool,
+    h1_max_uri_len: Option<usize>,
+    h1_allow_obsolete_line_folding: bool,
+    h1_parser_config: httparse::ParserConfig,
+    h1_proxy_protocol: bool,
+    h1_max_requests: Option<usize>,
     #[cfg(all(feature = "http1", feature = "runtime"))]
     h1_header_read_timeout: Option<Duration>,
     h1_writev: Option<bool>,
+    #[cfg(feature = "http1")]
+    h1_uri_validator: Option<proto::h1::UriValidator>,
+    #[cfg(feature = "http1")]
+    h1_on_response: Option<proto::ResponseHook>,
+    #[cfg(feature = "http1")]
+    h1_default_headers: HeaderMap,
     #[cfg(feature = "http2")]
     h2_builder: proto::h2::server::Config,
     mode: ConnectionMode,
     max_buf_size: Option<usize>,
+    h1_read_buf_initial_capacity: Option<usize>,
+    max_request_body_size: Option<u64>,
     pipeline_flush: bool,
+    #[cfg(all(feature = "http1", feature = "runtime"))]
+    write_flush_delay: Option<Duration>,
+    #[cfg(all(feature = "http1", feature = "runtime"))]
+    write_timeout: Option<Duration>,
 }
 
 /// The internal mode of HTTP protocol which indicates the behavior when a parse error occurs.
@@ -273,6 +296,12 @@ pub struct Parts<T, S> {
     _inner: (),
 }
 
+/// An error returned when configuring `Http` with an invalid value.
+#[derive(Debug)]
+#[cfg(feature = "http1")]
+#[cfg_attr(docsrs, doc(cfg(feature = "http1")))]
+pub struct ConfigError(&'static str);
+
 // ===== impl Http =====
 
 #[cfg(any(feature = "http1", feature = "http2"))]
@@ -286,14 +315,35 @@ impl Http {
             h1_keep_alive: true,
             h1_title_case_headers: false,
             h1_preserve_header_case: false,
+            h1_require_host_header: false,
+            h1_strict_expect: false,
+            h1_allow_connect: true,
+            h1_0_no_content_length: false,
+            h1_max_uri_len: None,
+            h1_allow_obsolete_line_folding: false,
+            h1_parser_config: Default::default(),
+            h1_proxy_protocol: false,
+            h1_max_requests: None,
             #[cfg(all(feature = "http1", feature = "runtime"))]
             h1_header_read_timeout: None,
             h1_writev: None,
+            #[cfg(feature = "http1")]
+            h1_uri_validator: None,
+            #[cfg(feature = "http1")]
+            h1_on_response: None,
+            #[cfg(feature = "http1")]
+            h1_default_headers: HeaderMap::new(),
             #[cfg(feature = "http2")]
             h2_builder: Default::default(),
             mode: ConnectionMode::default(),
             max_buf_size: None,
+            h1_read_buf_initial_capacity: None,
+            max_request_body_size: None,
             pipeline_flush: false,
+            #[cfg(all(feature = "http1", feature = "runtime"))]
+            write_flush_delay: None,
+            #[cfg(all(feature = "http1", feature = "runtime"))]
+            write_timeout: None,
         }
     }
 }
@@ -406,6 +456,169 @@ impl<E> Http<E> {
         self
     }
 
+    /// Set a function to validate the request-target of incoming HTTP/1
+    /// requests.
+    ///
+    /// The function is called with the parsed `Uri` of each request. If it
+    /// returns `false`, the request is rejected as if its URI was malformed:
+    /// a `400 Bad Request` response is written and the connection is closed.
+    ///
+    /// This can be used to, for example, only accept origin-form targets.
+    ///
+    /// Default is to accept any request-target that parses.
+    #[cfg(feature = "http1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http1")))]
+    pub fn http1_uri_validator<F>(&mut self, validator: F) -> &mut Self
+    where
+        F: Fn(&crate::Uri) -> bool + Send + Sync + 'static,
+    {
+        self.h1_uri_validator = Some(proto::h1::UriValidator::new(validator));
+        self
+    }
+
+    /// Set whether HTTP/1.1 requests without a `Host` header should be
+    /// rejected.
+    ///
+    /// RFC 7230 requires every HTTP/1.1 request to include a `Host` header.
+    /// If enabled, such a request is answered with a `400 Bad Request`
+    /// response and the connection is closed. HTTP/1.0 requests are not
+    /// affected.
+    ///
+    /// Default is false.
+    #[cfg(feature = "http1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http1")))]
+    pub fn http1_require_host_header(&mut self, enabled: bool) -> &mut Self {
+        self.h1_require_host_header = enabled;
+        self
+    }
+
+    /// Set whether HTTP/1 requests with an `Expect` header other than
+    /// `100-continue` should be rejected.
+    ///
+    /// If enabled, a request carrying an unknown expectation is answered
+    /// with a `417 Expectation Failed` response and the connection is
+    /// closed. Otherwise unknown expectations are ignored.
+    ///
+    /// Default is false.
+    #[cfg(feature = "http1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http1")))]
+    pub fn http1_strict_expect(&mut self, enabled: bool) -> &mut Self {
+        self.h1_strict_expect = enabled;
+        self
+    }
+
+    /// Set whether HTTP/1 `CONNECT` requests are allowed.
+    ///
+    /// If disabled, a `CONNECT` request is answered with a
+    /// `405 Method Not Allowed` response, whose `Allow` header lists the
+    /// other standard methods, and the connection is closed, without
+    /// calling the service.
+    ///
+    /// Default is true.
+    #[cfg(feature = "http1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http1")))]
+    pub fn http1_allow_connect(&mut self, enabled: bool) -> &mut Self {
+        self.h1_allow_connect = enabled;
+        self
+    }
+
+    /// Set whether responses to HTTP/1.0 requests omit `Content-Length`.
+    ///
+    /// If enabled, a response to an HTTP/1.0 request never has a
+    /// `Content-Length` header, even one set by the service. Its body is
+    /// instead delimited by closing the connection once it has been
+    /// written. Some HTTP/1.0 clients misbehave when given an explicit
+    /// length.
+    ///
+    /// Default is false.
+    #[cfg(feature = "http1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http1")))]
+    pub fn http1_0_no_content_length(&mut self, enabled: bool) -> &mut Self {
+        self.h1_0_no_content_length = enabled;
+        self
+    }
+
+    /// Set the maximum length of an HTTP/1 request-target.
+    ///
+    /// A request whose target is longer than this is answered with a
+    /// `414 URI Too Long` response and the connection is closed.
+    ///
+    /// Default is 65534 bytes, the largest target hyper can parse.
+    #[cfg(feature = "http1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http1")))]
+    pub fn http1_max_uri_length(&mut self, max: usize) -> &mut Self {
+        self.h1_max_uri_len = Some(max);
+        self
+    }
+
+    /// Set whether HTTP/1 request headers may use obsolete line folding.
+    ///
+    /// Obsolete line folding is a header value continued on the next line,
+    /// which starts with a space or tab. It was deprecated by RFC 7230, but
+    /// some legacy clients still send it. If enabled, each fold is replaced
+    /// with spaces, joining the continuation lines into a single header
+    /// value. Otherwise, such requests are answered with `400 Bad Request`.
+    ///
+    /// Default is false.
+    #[cfg(feature = "http1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http1")))]
+    pub fn http1_allow_obsolete_line_folding(&mut self, enabled: bool) -> &mut Self {
+        self.h1_allow_obsolete_line_folding = enabled;
+        self
+    }
+
+    /// Set whether HTTP/1 request lines may use more than one space between
+    /// the method, request-target and version.
+    ///
+    /// Some embedded clients send request lines such as
+    /// `GET  /  HTTP/1.1`. By default these are answered with
+    /// `400 Bad Request`; enabling this option accepts them instead.
+    ///
+    /// Note that this setting does not affect HTTP/2.
+    ///
+    /// Default is false.
+    #[cfg(feature = "http1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http1")))]
+    pub fn http1_lenient_request_line(&mut self, enabled: bool) -> &mut Self {
+        self.h1_parser_config
+            .allow_multiple_spaces_in_request_line_delimiters(enabled);
+        self
+    }
+
+    /// Set whether connections start with a PROXY protocol header.
+    ///
+    /// If enabled, each connection must begin with a version 1 PROXY
+    /// protocol line, such as `PROXY TCP4 192.0.2.1 198.51.100.1 56324 443`,
+    /// as sent by TCP load balancers. The line is consumed before any HTTP
+    /// is parsed, and the announced addresses are inserted into the
+    /// extensions of every request as [`ProxyAddrs`](crate::ext::ProxyAddrs).
+    /// A connection with a missing or malformed header is closed.
+    ///
+    /// Note that this setting does not affect HTTP/2.
+    ///
+    /// Default is false.
+    #[cfg(feature = "http1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http1")))]
+    pub fn accept_proxy_protocol(&mut self, enabled: bool) -> &mut Self {
+        self.h1_proxy_protocol = enabled;
+        self
+    }
+
+    /// Set the maximum number of requests served on each HTTP/1 connection.
+    ///
+    /// Once this many requests have been read, the response to the last one
+    /// includes a `Connection: close` header, and the connection is closed
+    /// after it has been written. This lets a server cycle long-lived
+    /// keep-alive connections.
+    ///
+    /// Default is no limit.
+    #[cfg(feature = "http1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http1")))]
+    pub fn http1_max_requests_per_connection(&mut self, max: usize) -> &mut Self {
+        self.h1_max_requests = Some(max);
+        self
+    }
+
     /// Sets whether HTTP2 is required.
     ///
     /// Default is false
@@ -491,6 +704,19 @@ impl<E> Http<E> {
         self
     }
 
+    /// Sets the max size of received header frames for HTTP2.
+    ///
+    /// This is advertised to the peer as `SETTINGS_MAX_HEADER_LIST_SIZE`.
+    /// APIs that send many or very large headers may need to raise it.
+    ///
+    /// Default is currently 16MB, but may change.
+    #[cfg(feature = "http2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
+    pub fn http2_max_header_list_size(&mut self, max: u32) -> &mut Self {
+        self.h2_builder.max_header_list_size = max;
+        self
+    }
+
     /// Sets the [`SETTINGS_MAX_CONCURRENT_STREAMS`][spec] option for HTTP2
     /// connections.
     ///
@@ -576,6 +802,58 @@ impl<E> Http<E> {
         self
     }
 
+    /// Set the maximum buffer size for the connection, without panicking.
+    ///
+    /// This is the same as [`max_buf_size`](Http::max_buf_size), except
+    /// that a `max` smaller than the minimum of 8192 returns an error and
+    /// leaves the configuration unchanged.
+    #[cfg(feature = "http1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http1")))]
+    pub fn try_max_buf_size(&mut self, max: usize) -> Result<&mut Self, ConfigError> {
+        if max < proto::h1::MINIMUM_MAX_BUFFER_SIZE {
+            return Err(ConfigError(
+                "max_buf_size is smaller than the minimum that h1 specifies",
+            ));
+        }
+        self.max_buf_size = Some(max);
+        Ok(self)
+    }
+
+    /// Set the initial size of the read buffer for HTTP/1 connections.
+    ///
+    /// The read buffer grows and shrinks with the amount of data each read
+    /// returns, starting from this size. Starting larger avoids
+    /// reallocating the buffer several times when receiving large bodies.
+    ///
+    /// The value is kept between 8192 and the [maximum buffer
+    /// size](Http::max_buf_size).
+    ///
+    /// Default is 8192.
+    #[cfg(feature = "http1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http1")))]
+    pub fn http1_read_buf_initial_capacity(&mut self, cap: usize) -> &mut Self {
+        self.h1_read_buf_initial_capacity = Some(cap);
+        self
+    }
+
+    /// Set the maximum size of an HTTP/1 request body.
+    ///
+    /// If a request declares a `Content-Length` over this limit, a
+    /// `413 Payload Too Large` response is sent without calling the service.
+    /// If a chunked body grows past it while being read, the request body
+    /// yields an error for which [`Error::is_body_too_large`] is true.
+    /// Either way, the connection is closed afterwards.
+    ///
+    /// Default is no limit.
+    ///
+    /// [`Error::is_body_too_large`]: crate::Error::is_body_too_large
+    #[cfg(feature = "http1")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http1")))]
+    pub fn max_request_body_size(&mut self, max: u64) -> &mut Self {
+        self.max_request_body_size = Some(max);
+        self
+    }
+
     /// Aggregates flushes to better support pipelined responses.
     ///
     /// Experimental, may have bugs.
@@ -586,6 +864,95 @@ impl<E> Http<E> {
         self
     }
 
+    /// Delays flushing while a response body is being written, so that
+    /// several small body chunks can be coalesced into a single write.
+    ///
+    /// Buffered bytes are flushed once the delay has passed, when the write
+    /// buffer is full, or when the response body is finished.
+    ///
+    /// Note that this setting does not affect HTTP/2.
+    ///
+    /// Default is `None`, flushing immediately.
+    #[cfg(all(feature = "http1", feature = "runtime"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "http1", feature = "runtime"))))]
+    pub fn write_flush_delay(&mut self, delay: Option<Duration>) -> &mut Self {
+        self.write_flush_delay = delay;
+        self
+    }
+
+    /// Set a timeout for writing to the client. If a write makes no
+    /// progress within this time, such as when the client has stopped
+    /// reading, the connection is closed with a timeout error.
+    ///
+    /// Note that this setting does not affect HTTP/2.
+    ///
+    /// Default is None.
+    #[cfg(all(feature = "http1", feature = "runtime"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "http1", feature = "runtime"))))]
+    pub fn write_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// Set a function to inspect or modify each response before it is
+    /// written.
+    ///
+    /// The function is called with the head of every response, both those
+    /// returned by the service and those hyper sends itself, such as a
+    /// `400 Bad Request` for a request that failed to parse. The body is
+    /// not available to it. This can be used to, for example, add a
+    /// `Server` header or security headers to every response.
+    ///
+    /// Headers that hyper manages, like `Content-Length` and `Date`, are
+    /// set after the function runs.
+    pub fn on_response<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&mut crate::Response<()>) + Send + Sync + 'static,
+    {
+        let hook = proto::ResponseHook::new(f);
+        #[cfg(feature = "http2")]
+        {
+            self.h2_builder.on_response = Some(hook.clone());
+        }
+        #[cfg(feature = "http1")]
+        {
+            self.h1_on_response = Some(hook);
+        }
+        self
+    }
+
+    /// Set a `Server` header to add to responses that don't have one.
+    ///
+    /// If a response returned by the service, or sent by hyper itself,
+    /// lacks a `Server` header, this value is inserted. A `Server` header
+    /// set by the service is left as is. The header is added before any
+    /// [`on_response`](Http::on_response) function runs.
+    ///
+    /// Passing `None` disables it. Default is `None`.
+    pub fn default_server_header(&mut self, value: Option<HeaderValue>) -> &mut Self {
+        self.default_header(http::header::SERVER, value)
+    }
+
+    /// Set an `Alt-Svc` header to add to responses that don't have one.
+    ///
+    /// This advertises alternative services, such as an HTTP/3 endpoint,
+    /// to clients. An `Alt-Svc` header set by the service is left as is.
+    /// The header is added before any [`on_response`](Http::on_response)
+    /// function runs.
+    ///
+    /// Passing `None` disables it. Default is `None`.
+    pub fn alt_svc(&mut self, value: Option<HeaderValue>) -> &mut Self {
+        self.default_header(http::header::ALT_SVC, value)
+    }
+
+    fn default_header(&mut self, name: HeaderName, value: Option<HeaderValue>) -> &mut Self {
+        #[cfg(feature = "http1")]
+        set_or_remove(&mut self.h1_default_headers, &name, value.as_ref());
+        #[cfg(feature = "http2")]
+        set_or_remove(&mut self.h2_builder.default_headers, &name, value.as_ref());
+        self
+    }
+
     /// Set the executor used to spawn background tasks.
     ///
     /// Default uses implicit default (like `tokio::spawn`).
@@ -596,14 +963,35 @@ impl<E> Http<E> {
             h1_keep_alive: self.h1_keep_alive,
             h1_title_case_headers: self.h1_title_case_headers,
             h1_preserve_header_case: self.h1_preserve_header_case,
+            h1_require_host_header: self.h1_require_host_header,
+            h1_strict_expect: self.h1_strict_expect,
+            h1_allow_connect: self.h1_allow_connect,
+            h1_0_no_content_length: self.h1_0_no_content_length,
+            h1_max_uri_len: self.h1_max_uri_len,
+            h1_allow_obsolete_line_folding: self.h1_allow_obsolete_line_folding,
+            h1_parser_config: self.h1_parser_config,
+            h1_proxy_protocol: self.h1_proxy_protocol,
+            h1_max_requests: self.h1_max_requests,
             #[cfg(all(feature = "http1", feature = "runtime"))]
             h1_header_read_timeout: self.h1_header_read_timeout,
             h1_writev: self.h1_writev,
+            #[cfg(feature = "http1")]
+            h1_uri_validator: self.h1_uri_validator,
+            #[cfg(feature = "http1")]
+            h1_on_response: self.h1_on_response,
+            #[cfg(feature = "http1")]
+            h1_default_headers: self.h1_default_headers,
             #[cfg(feature = "http2")]
             h2_builder: self.h2_builder,
             mode: self.mode,
             max_buf_size: self.max_buf_size,
+            h1_read_buf_initial_capacity: self.h1_read_buf_initial_capacity,
+            max_request_body_size: self.max_request_body_size,
             pipeline_flush: self.pipeline_flush,
+            #[cfg(all(feature = "http1", feature = "runtime"))]
+            write_flush_delay: self.write_flush_delay,
+            #[cfg(all(feature = "http1", feature = "runtime"))]
+            write_timeout: self.write_timeout,
         }
     }
 
@@ -636,6 +1024,55 @@ impl<E> Http<E> {
     /// # fn main() {}
     /// ```
     pub fn serve_connection<S, I, Bd>(&self, io: I, service: S) -> Connection<I, S, E>
+    where
+        S: HttpService<Body, ResBody = Bd>,
+        S::Error: Into<Box<dyn StdError + Send + Sync>>,
+        Bd: HttpBody + 'static,
+        Bd::Error: Into<Box<dyn StdError + Send + Sync>>,
+        I: AsyncRead + AsyncWrite + Unpin,
+        E: ConnStreamExec<S::Future, Bd>,
+    {
+        self.serve_connection_with_mode(io, service, self.mode.clone())
+    }
+
+    /// Bind a connection together with a [`Service`](crate::service::Service),
+    /// choosing the protocol from a negotiated ALPN token.
+    ///
+    /// A TLS stream that negotiated ALPN already knows which protocol the
+    /// peer will speak, but hyper can't see it. Passing the negotiated token
+    /// here serves the connection with HTTP/2 if it is `h2`, and with
+    /// HTTP/1 otherwise, instead of the mode configured on this `Http`.
+    pub fn serve_connection_with_alpn<S, I, Bd>(
+        &self,
+        io: I,
+        service: S,
+        alpn: Option<&[u8]>,
+    ) -> Connection<I, S, E>
+    where
+        S: HttpService<Body, ResBody = Bd>,
+        S::Error: Into<Box<dyn StdError + Send + Sync>>,
+        Bd: HttpBody + 'static,
+        Bd::Error: Into<Box<dyn StdError + Send + Sync>>,
+        I: AsyncRead + AsyncWrite + Unpin,
+        E: ConnStreamExec<S::Future, Bd>,
+    {
+        let mode = match alpn {
+            #[cfg(feature = "http2")]
+            Some(b"h2") => ConnectionMode::H2Only,
+            #[cfg(feature = "http1")]
+            _ => ConnectionMode::H1Only,
+            #[cfg(not(feature = "http1"))]
+            _ => self.mode.clone(),
+        };
+        self.serve_connection_with_mode(io, service, mode)
+    }
+
+    fn serve_connection_with_mode<S, I, Bd>(
+        &self,
+        io: I,
+        service: S,
+        mode: ConnectionMode,
+    ) -> Connection<I, S, E>
     where
         S: HttpService<Body, ResBody = Bd>,
         S::Error: Into<Box<dyn StdError + Send + Sync>>,
@@ -660,6 +1097,31 @@ impl<E> Http<E> {
                 if self.h1_preserve_header_case {
                     conn.set_preserve_header_case();
                 }
+                if self.h1_require_host_header {
+                    conn.set_require_host_header();
+                }
+                if self.h1_strict_expect {
+                    conn.set_strict_expect();
+                }
+                if !self.h1_allow_connect {
+                    conn.set_reject_connect();
+                }
+                if let Some(max) = self.h1_max_uri_len {
+                    conn.set_max_uri_len(max);
+                }
+                if self.h1_allow_obsolete_line_folding {
+                    conn.set_allow_obsolete_line_folding();
+                }
+                conn.set_h1_parser_config(self.h1_parser_config.clone());
+                if self.h1_0_no_content_length {
+                    conn.set_http10_no_content_length();
+                }
+                if self.h1_proxy_protocol {
+                    conn.set_accept_proxy_protocol();
+                }
+                if let Some(max) = self.h1_max_requests {
+                    conn.set_max_requests(max);
+                }
                 #[cfg(all(feature = "http1", feature = "runtime"))]
                 if let Some(header_read_timeout) = self.h1_header_read_timeout {
                     conn.set_http1_header_read_timeout(header_read_timeout);
@@ -671,10 +1133,33 @@ impl<E> Http<E> {
                         conn.set_write_strategy_flatten();
                     }
                 }
+                if let Some(ref validator) = self.h1_uri_validator {
+                    conn.set_uri_validator(validator.clone());
+                }
+                if let Some(ref hook) = self.h1_on_response {
+                    conn.set_on_response(hook.clone());
+                }
+                if !self.h1_default_headers.is_empty() {
+                    conn.set_default_headers(self.h1_default_headers.clone());
+                }
                 conn.set_flush_pipeline(self.pipeline_flush);
+                #[cfg(all(feature = "http1", feature = "runtime"))]
+                if let Some(delay) = self.write_flush_delay {
+                    conn.set_write_flush_delay(delay);
+                }
+                #[cfg(all(feature = "http1", feature = "runtime"))]
+                if let Some(timeout) = self.write_timeout {
+                    conn.set_write_timeout(timeout);
+                }
                 if let Some(max) = self.max_buf_size {
                     conn.set_max_buf_size(max);
                 }
+                if let Some(cap) = self.h1_read_buf_initial_capacity {
+                    conn.set_read_buf_initial_capacity(cap);
+                }
+                if let Some(max) = self.max_request_body_size {
+                    conn.set_max_body_size(max);
+                }
                 let sd = proto::h1::dispatch::Server::new(service);
                 ProtoServer::H1 {
                     h1: proto::h1::Dispatcher::new(sd, conn),
@@ -682,7 +1167,7 @@ impl<E> Http<E> {
             }};
         }
 
-        let proto = match self.mode {
+        let proto = match mode {
             #[cfg(feature = "http1")]
             #[cfg(not(feature = "http2"))]
             ConnectionMode::H1Only => h1!(),
@@ -701,7 +1186,7 @@ impl<E> Http<E> {
         Connection {
             conn: Some(proto),
             #[cfg(all(feature = "http1", feature = "http2"))]
-            fallback: if self.mode == ConnectionMode::Fallback {
+            fallback: if mode == ConnectionMode::Fallback {
                 Fallback::ToHttp2(self.h2_builder.clone(), self.exec.clone())
             } else {
                 Fallback::Http1Only
@@ -729,6 +1214,30 @@ impl<E> Http<E> {
     }
 }
 
+#[cfg(any(feature = "http1", feature = "http2"))]
+fn set_or_remove(headers: &mut HeaderMap, name: &HeaderName, value: Option<&HeaderValue>) {
+    match value {
+        Some(value) => {
+            headers.insert(name.clone(), value.clone());
+        }
+        None => {
+            headers.remove(name);
+        }
+    }
+}
+
+// ===== impl ConfigError =====
+
+#[cfg(feature = "http1")]
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+#[cfg(feature = "http1")]
+impl StdError for ConfigError {}
+
 // ===== impl Connection =====
 
 #[cfg(any(feature = "http1", feature = "http2"))]
@@ -893,10 +1402,18 @@ where
     /// Enable this connection to support higher-level HTTP upgrades.
     ///
     /// See [the `upgrade` module](crate::upgrade) for more.
-    pub fn with_upgrades(self) -> UpgradeableConnection<I, S, E>
+    pub fn with_upgrades(
+        #[cfg_attr(not(feature = "http1"), allow(unused_mut))] mut self,
+    ) -> UpgradeableConnection<I, S, E>
     where
         I: Send,
     {
+        // `Upgraded` writes out anything left unflushed before new data, so
+        // there's no need to wait for the response to be flushed.
+        #[cfg(feature = "http1")]
+        if let Some(ProtoServer::H1 { ref mut h1, .. }) = self.conn {
+            h1.set_upgrade_without_flush();
+        }
         UpgradeableConnection { inner: self }
     }
 }
@@ -1339,9 +1856,10 @@ mod upgrades {
                     #[cfg(feature = "http1")]
                     Ok(proto::Dispatched::Upgrade(pending)) => {
                         match self.inner.conn.take() {
-                            Some(ProtoServer::H1 { h1, .. }) => {
+                            Some(ProtoServer::H1 { mut h1, .. }) => {
+                                let write_buf = h1.take_write_buf();
                                 let (io, buf, _) = h1.into_inner();
-                                pending.fulfill(Upgraded::new(io, buf));
+                                pending.fulfill(Upgraded::new(io, buf).with_write_buf(write_buf));
                                 return Poll::Ready(Ok(()));
                             }
                             _ => {
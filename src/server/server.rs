@@ -392,6 +392,19 @@ impl<I, E> Builder<I, E> {
         self
     }
 
+    /// Sets the max size of received header frames for HTTP2.
+    ///
+    /// This is advertised to the peer as `SETTINGS_MAX_HEADER_LIST_SIZE`.
+    /// APIs that send many or very large headers may need to raise it.
+    ///
+    /// Default is currently 16MB, but may change.
+    #[cfg(feature = "http2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http2")))]
+    pub fn http2_max_header_list_size(mut self, max: u32) -> Self {
+        self.protocol.http2_max_header_list_size(max);
+        self
+    }
+
     /// Sets the [`SETTINGS_MAX_CONCURRENT_STREAMS`][spec] option for HTTP2
     /// connections.
     ///
@@ -548,4 +561,39 @@ impl<E> Builder<AddrIncoming, E> {
         self.incoming.set_sleep_on_errors(val);
         self
     }
+
+    /// Set a function to check the remote address of each accepted connection.
+    ///
+    /// Returning `false` closes the socket before any HTTP processing.
+    ///
+    /// For more details see [`AddrIncoming::set_on_accept`]
+    pub fn on_accept<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(SocketAddr) -> bool + Send + Sync + 'static,
+    {
+        self.incoming.set_on_accept(f);
+        self
+    }
+
+    /// Set a function to be notified of each established connection.
+    ///
+    /// For more details see [`AddrIncoming::set_on_connection`]
+    pub fn on_connection<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&SocketAddr) + Send + Sync + 'static,
+    {
+        self.incoming.set_on_connection(f);
+        self
+    }
+
+    /// Set a function to be notified of each error from accepting a connection.
+    ///
+    /// For more details see [`AddrIncoming::set_on_accept_error`]
+    pub fn on_accept_error<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&std::io::Error) + Send + Sync + 'static,
+    {
+        self.incoming.set_on_accept_error(f);
+        self
+    }
 }
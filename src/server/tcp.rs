@@ -13,6 +13,9 @@ use crate::common::{task, Future, Pin, Poll};
 pub use self::addr_stream::AddrStream;
 use super::accept::Accept;
 
+type OnConnection = Box<dyn Fn(&SocketAddr) + Send + Sync>;
+type OnAcceptError = Box<dyn FnMut(&io::Error) + Send + Sync>;
+
 /// A stream of connections from binding to an address.
 #[must_use = "streams do nothing unless polled"]
 pub struct AddrIncoming {
@@ -22,6 +25,9 @@ pub struct AddrIncoming {
     tcp_keepalive_timeout: Option<Duration>,
     tcp_nodelay: bool,
     timeout: Option<Pin<Box<Sleep>>>,
+    on_accept: Option<Box<dyn FnMut(SocketAddr) -> bool + Send + Sync>>,
+    on_connection: Option<OnConnection>,
+    on_accept_error: Option<OnAcceptError>,
 }
 
 impl AddrIncoming {
@@ -55,6 +61,9 @@ impl AddrIncoming {
             tcp_keepalive_timeout: None,
             tcp_nodelay: false,
             timeout: None,
+            on_accept: None,
+            on_connection: None,
+            on_accept_error: None,
         })
     }
 
@@ -98,6 +107,49 @@ impl AddrIncoming {
         self.sleep_on_errors = val;
     }
 
+    /// Set a function to check the remote address of each accepted connection.
+    ///
+    /// The function is called as soon as a socket is accepted, before any
+    /// HTTP processing. If it returns `false`, the socket is closed right
+    /// away and the listener moves on to the next connection. This can be
+    /// used to implement IP allowlists or per-address rate limits.
+    pub fn set_on_accept<F>(&mut self, f: F) -> &mut Self
+    where
+        F: FnMut(SocketAddr) -> bool + Send + Sync + 'static,
+    {
+        self.on_accept = Some(Box::new(f));
+        self
+    }
+
+    /// Set a function to be notified of each established connection.
+    ///
+    /// The function is called once per connection that is handed off for
+    /// HTTP processing, before the first request is read. Unlike
+    /// [`set_on_accept`](AddrIncoming::set_on_accept), it cannot reject the
+    /// connection, and is meant for logging and metrics.
+    pub fn set_on_connection<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&SocketAddr) + Send + Sync + 'static,
+    {
+        self.on_connection = Some(Box::new(f));
+        self
+    }
+
+    /// Set a function to be notified of each error from accepting a connection.
+    ///
+    /// Errors that only affect a single connection are otherwise skipped
+    /// with a `debug` log, and errors such as `EMFILE` are logged and slept
+    /// on (see [`set_sleep_on_errors`](AddrIncoming::set_sleep_on_errors)).
+    /// The function is called for every such error before that handling,
+    /// so they can be reported and counted separately from service errors.
+    pub fn set_on_accept_error<F>(&mut self, f: F) -> &mut Self
+    where
+        F: FnMut(&io::Error) + Send + Sync + 'static,
+    {
+        self.on_accept_error = Some(Box::new(f));
+        self
+    }
+
     fn poll_next_(&mut self, cx: &mut task::Context<'_>) -> Poll<io::Result<AddrStream>> {
         // Check if a previous timeout is active that was set by IO errors.
         if let Some(ref mut to) = self.timeout {
@@ -108,6 +160,12 @@ impl AddrIncoming {
         loop {
             match ready!(self.listener.poll_accept(cx)) {
                 Ok((socket, addr)) => {
+                    if let Some(ref mut on_accept) = self.on_accept {
+                        if !on_accept(addr) {
+                            debug!("connection from {} rejected", addr);
+                            continue;
+                        }
+                    }
                     if let Some(dur) = self.tcp_keepalive_timeout {
                         let socket = socket2::SockRef::from(&socket);
                         let conf = socket2::TcpKeepalive::new().with_time(dur);
@@ -118,37 +176,53 @@ impl AddrIncoming {
                     if let Err(e) = socket.set_nodelay(self.tcp_nodelay) {
                         trace!("error trying to set TCP nodelay: {}", e);
                     }
+                    if let Some(ref on_connection) = self.on_connection {
+                        on_connection(&addr);
+                    }
                     return Poll::Ready(Ok(AddrStream::new(socket, addr)));
                 }
-                Err(e) => {
-                    // Connection errors can be ignored directly, continue by
-                    // accepting the next request.
-                    if is_connection_error(&e) {
-                        debug!("accepted connection already errored: {}", e);
-                        continue;
-                    }
+                Err(e) => ready!(self.poll_accept_error(e, cx))?,
+            }
+        }
+    }
 
-                    if self.sleep_on_errors {
-                        error!("accept error: {}", e);
-
-                        // Sleep 1s.
-                        let mut timeout = Box::pin(tokio::time::sleep(Duration::from_secs(1)));
-
-                        match timeout.as_mut().poll(cx) {
-                            Poll::Ready(()) => {
-                                // Wow, it's been a second already? Ok then...
-                                continue;
-                            }
-                            Poll::Pending => {
-                                self.timeout = Some(timeout);
-                                return Poll::Pending;
-                            }
-                        }
-                    } else {
-                        return Poll::Ready(Err(e));
-                    }
+    /// Handles an error from accepting a connection.
+    ///
+    /// Returns `Ready(Ok(()))` if the next connection should be accepted.
+    fn poll_accept_error(
+        &mut self,
+        e: io::Error,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        if let Some(ref mut on_accept_error) = self.on_accept_error {
+            on_accept_error(&e);
+        }
+
+        // Connection errors can be ignored directly, continue by
+        // accepting the next request.
+        if is_connection_error(&e) {
+            debug!("accepted connection already errored: {}", e);
+            return Poll::Ready(Ok(()));
+        }
+
+        if self.sleep_on_errors {
+            error!("accept error: {}", e);
+
+            // Sleep 1s.
+            let mut timeout = Box::pin(tokio::time::sleep(Duration::from_secs(1)));
+
+            match timeout.as_mut().poll(cx) {
+                Poll::Ready(()) => {
+                    // Wow, it's been a second already? Ok then...
+                    Poll::Ready(Ok(()))
+                }
+                Poll::Pending => {
+                    self.timeout = Some(timeout);
+                    Poll::Pending
                 }
             }
+        } else {
+            Poll::Ready(Err(e))
         }
     }
 }
@@ -186,6 +260,9 @@ impl fmt::Debug for AddrIncoming {
             .field("sleep_on_errors", &self.sleep_on_errors)
             .field("tcp_keepalive_timeout", &self.tcp_keepalive_timeout)
             .field("tcp_nodelay", &self.tcp_nodelay)
+            .field("on_accept", &self.on_accept.is_some())
+            .field("on_connection", &self.on_connection.is_some())
+            .field("on_accept_error", &self.on_accept_error.is_some())
             .finish()
     }
 }
@@ -300,3 +377,72 @@ mod addr_stream {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::AddrIncoming;
+    use crate::common::{Pin, Poll};
+    use crate::server::accept::Accept;
+
+    async fn accept_one(incoming: &mut AddrIncoming) -> tokio::net::TcpStream {
+        let _client = tokio::net::TcpStream::connect(incoming.local_addr())
+            .await
+            .unwrap();
+        let stream =
+            futures_util::future::poll_fn(|cx| match Pin::new(&mut *incoming).poll_accept(cx) {
+                Poll::Ready(Some(res)) => Poll::Ready(res),
+                Poll::Ready(None) => panic!("incoming closed"),
+                Poll::Pending => Poll::Pending,
+            })
+            .await
+            .unwrap();
+        stream.into_inner()
+    }
+
+    #[tokio::test]
+    async fn tcp_keepalive() {
+        let mut incoming = AddrIncoming::bind(&([127, 0, 0, 1], 0).into()).unwrap();
+
+        let socket = accept_one(&mut incoming).await;
+        assert!(!socket2::SockRef::from(&socket).keepalive().unwrap());
+
+        incoming.set_keepalive(Some(Duration::from_secs(60)));
+        let socket = accept_one(&mut incoming).await;
+        assert!(socket2::SockRef::from(&socket).keepalive().unwrap());
+    }
+
+    #[tokio::test]
+    async fn on_accept_error() {
+        let mut incoming = AddrIncoming::bind(&([127, 0, 0, 1], 0).into()).unwrap();
+        incoming.set_sleep_on_errors(false);
+        let kinds = Arc::new(Mutex::new(Vec::new()));
+        let kinds2 = kinds.clone();
+        incoming.set_on_accept_error(move |e| kinds2.lock().unwrap().push(e.kind()));
+
+        // connection errors are still skipped
+        let mut err = Some(io::Error::from(io::ErrorKind::ConnectionReset));
+        futures_util::future::poll_fn(|cx| incoming.poll_accept_error(err.take().unwrap(), cx))
+            .await
+            .unwrap();
+        assert_eq!(*kinds.lock().unwrap(), [io::ErrorKind::ConnectionReset]);
+
+        let mut err = Some(io::Error::from(io::ErrorKind::Other));
+        futures_util::future::poll_fn(|cx| incoming.poll_accept_error(err.take().unwrap(), cx))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            *kinds.lock().unwrap(),
+            [io::ErrorKind::ConnectionReset, io::ErrorKind::Other]
+        );
+    }
+
+    #[test]
+    fn addr_incoming_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<AddrIncoming>();
+    }
+}
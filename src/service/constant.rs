@@ -0,0 +1,56 @@
+use std::convert::Infallible;
+
+use bytes::Bytes;
+use futures_util::future::{self, Ready};
+
+use crate::common::{task, Poll};
+use crate::header::HeaderMap;
+use crate::{Body, Request, Response, StatusCode};
+
+/// Create a `Service` that always replies with the same response.
+///
+/// Every call returns a response with the given status, headers and body.
+/// The body is cheaply cloned for each call. This is handy for health
+/// check endpoints and tests.
+///
+/// # Example
+///
+/// ```
+/// use hyper::StatusCode;
+/// use hyper::header::HeaderMap;
+/// use hyper::service::constant;
+///
+/// let healthz = constant(StatusCode::OK, HeaderMap::new(), "ok");
+/// ```
+pub fn constant(status: StatusCode, headers: HeaderMap, body: impl Into<Bytes>) -> Constant {
+    Constant {
+        status,
+        headers,
+        body: body.into(),
+    }
+}
+
+/// Service returned by [`constant`]
+#[derive(Clone, Debug)]
+pub struct Constant {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl<ReqBody> tower_service::Service<Request<ReqBody>> for Constant {
+    type Response = Response<Body>;
+    type Error = Infallible;
+    type Future = Ready<Result<Response<Body>, Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _req: Request<ReqBody>) -> Self::Future {
+        let mut res = Response::new(Body::from(self.body.clone()));
+        *res.status_mut() = self.status;
+        *res.headers_mut() = self.headers.clone();
+        future::ok(res)
+    }
+}
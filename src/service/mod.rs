@@ -37,10 +37,13 @@
 
 pub use tower_service::Service;
 
+mod constant;
 mod http;
 mod make;
 #[cfg(all(any(feature = "http1", feature = "http2"), feature = "client"))]
 mod oneshot;
+#[cfg(feature = "runtime")]
+mod timeout;
 mod util;
 
 pub(super) use self::http::HttpService;
@@ -51,5 +54,9 @@ pub(super) use self::make::MakeServiceRef;
 #[cfg(all(any(feature = "http1", feature = "http2"), feature = "client"))]
 pub(super) use self::oneshot::{oneshot, Oneshot};
 
+pub use self::constant::constant;
 pub use self::make::make_service_fn;
+#[cfg(feature = "runtime")]
+#[cfg_attr(docsrs, doc(cfg(feature = "runtime")))]
+pub use self::timeout::timeout;
 pub use self::util::service_fn;
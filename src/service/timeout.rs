@@ -0,0 +1,150 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::time::Duration;
+
+use pin_project_lite::pin_project;
+use tokio::time::Sleep;
+use tower_service::Service;
+use tracing::debug;
+
+use crate::common::{task, Future, Pin, Poll};
+use crate::error::TimedOut;
+use crate::{Request, Response, StatusCode};
+
+/// Wrap a `Service`, limiting how long each call may take.
+///
+/// If a call to the wrapped service has not completed after `duration`,
+/// its future is dropped, and a `503 Service Unavailable` response with an
+/// empty body is returned instead. This protects a server from handlers
+/// that are slow to respond.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use hyper::{Body, Request, Response, StatusCode};
+/// use hyper::service::{service_fn, timeout};
+///
+/// let service = timeout(
+///     service_fn(|_req: Request<Body>| async move {
+///         Ok::<_, hyper::Error>(Response::new(Body::from("Hello World")))
+///     }),
+///     Duration::from_secs(30),
+/// )
+/// .with_status(StatusCode::GATEWAY_TIMEOUT);
+/// ```
+///
+/// # Optional
+///
+/// This function requires enabling the `runtime` feature in your
+/// `Cargo.toml`.
+pub fn timeout<S>(service: S, duration: Duration) -> Timeout<S> {
+    Timeout {
+        inner: service,
+        duration,
+        on_timeout: OnTimeout::Respond(StatusCode::SERVICE_UNAVAILABLE),
+    }
+}
+
+/// Service returned by [`timeout`]
+#[derive(Clone, Debug)]
+pub struct Timeout<S> {
+    inner: S,
+    duration: Duration,
+    on_timeout: OnTimeout,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum OnTimeout {
+    Respond(StatusCode),
+    Fail,
+}
+
+impl<S> Timeout<S> {
+    /// Sets the status of the response returned when a call times out.
+    ///
+    /// Default is `503 Service Unavailable`.
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.on_timeout = OnTimeout::Respond(status);
+        self
+    }
+
+    /// Makes a call that times out fail with an error, instead of returning
+    /// a response.
+    ///
+    /// A server will close the connection when this happens, and the error
+    /// it returns reports `true` from [`Error::is_timeout`](crate::Error::is_timeout).
+    pub fn with_error(mut self) -> Self {
+        self.on_timeout = OnTimeout::Fail;
+        self
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for Timeout<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Error: Into<Box<dyn StdError + Send + Sync>>,
+    ResBody: Default,
+{
+    type Response = Response<ResBody>;
+    type Error = Box<dyn StdError + Send + Sync>;
+    type Future = TimeoutFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        TimeoutFuture {
+            inner: self.inner.call(req),
+            sleep: tokio::time::sleep(self.duration),
+            on_timeout: self.on_timeout,
+        }
+    }
+}
+
+pin_project! {
+    /// Future returned by a [`Timeout`] service.
+    pub struct TimeoutFuture<F> {
+        #[pin]
+        inner: F,
+        #[pin]
+        sleep: Sleep,
+        on_timeout: OnTimeout,
+    }
+}
+
+impl<F, ResBody, E> Future for TimeoutFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    E: Into<Box<dyn StdError + Send + Sync>>,
+    ResBody: Default,
+{
+    type Output = Result<Response<ResBody>, Box<dyn StdError + Send + Sync>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let me = self.project();
+        if let Poll::Ready(res) = me.inner.poll(cx) {
+            return Poll::Ready(res.map_err(Into::into));
+        }
+
+        ready!(me.sleep.poll(cx));
+        debug!("service call timed out");
+        match *me.on_timeout {
+            OnTimeout::Respond(status) => {
+                let mut res = Response::new(ResBody::default());
+                *res.status_mut() = status;
+                Poll::Ready(Ok(res))
+            }
+            OnTimeout::Fail => Poll::Ready(Err(Box::new(TimedOut))),
+        }
+    }
+}
+
+impl<F> fmt::Debug for TimeoutFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimeoutFuture")
+            .field("on_timeout", &self.on_timeout)
+            .finish()
+    }
+}
@@ -45,7 +45,7 @@ use std::fmt;
 use std::io;
 use std::marker::Unpin;
 
-use bytes::Bytes;
+use bytes::{Buf, Bytes};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::sync::oneshot;
 #[cfg(any(feature = "http1", feature = "http2"))]
@@ -64,6 +64,7 @@ use crate::common::{task, Future, Pin, Poll};
 /// into its parts.
 pub struct Upgraded {
     io: Rewind<Box<dyn Io + Send>>,
+    write_buf: Bytes,
 }
 
 /// A future for a possible HTTP upgrade.
@@ -90,6 +91,15 @@ pub struct Parts<T> {
     /// You will want to check for any existing bytes if you plan to continue
     /// communicating on the IO object.
     pub read_buf: Bytes,
+    /// A buffer of bytes that were queued to be written by the HTTP state
+    /// machine, but not yet flushed to the IO object.
+    ///
+    /// For instance, if the IO object was not writable when the upgrade
+    /// completed, the end of the upgrade response may still be in here.
+    ///
+    /// You will want to write these bytes first if you plan to continue
+    /// communicating on the IO object.
+    pub write_buf: Bytes,
     _inner: (),
 }
 
@@ -126,9 +136,25 @@ impl Upgraded {
     {
         Upgraded {
             io: Rewind::new_buffered(Box::new(io), read_buf),
+            write_buf: Bytes::new(),
         }
     }
 
+    #[cfg(any(feature = "http1", test))]
+    pub(super) fn with_write_buf(mut self, write_buf: Bytes) -> Self {
+        self.write_buf = write_buf;
+        self
+    }
+
+    /// Returns the bytes that were queued to be written before the upgrade,
+    /// but not yet flushed to the underlying IO.
+    ///
+    /// These are written out before anything else written to this
+    /// `Upgraded`, so most callers don't need to check them.
+    pub fn write_buf(&self) -> &[u8] {
+        &self.write_buf
+    }
+
     /// Tries to downcast the internal trait object to the type passed.
     ///
     /// On success, returns the downcasted parts. On error, returns the
@@ -139,13 +165,26 @@ impl Upgraded {
             Ok(t) => Ok(Parts {
                 io: *t,
                 read_buf: buf,
+                write_buf: self.write_buf,
                 _inner: (),
             }),
             Err(io) => Err(Upgraded {
                 io: Rewind::new_buffered(io, buf),
+                write_buf: self.write_buf,
             }),
         }
     }
+
+    fn poll_write_buf(&mut self, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        while !self.write_buf.is_empty() {
+            let n = ready!(Pin::new(&mut self.io).poll_write(cx, &self.write_buf))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.write_buf.advance(n);
+        }
+        Poll::Ready(Ok(()))
+    }
 }
 
 impl AsyncRead for Upgraded {
@@ -164,6 +203,7 @@ impl AsyncWrite for Upgraded {
         cx: &mut task::Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_write_buf(cx))?;
         Pin::new(&mut self.io).poll_write(cx, buf)
     }
 
@@ -172,14 +212,17 @@ impl AsyncWrite for Upgraded {
         cx: &mut task::Context<'_>,
         bufs: &[io::IoSlice<'_>],
     ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_write_buf(cx))?;
         Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
     }
 
     fn poll_flush(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write_buf(cx))?;
         Pin::new(&mut self.io).poll_flush(cx)
     }
 
     fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write_buf(cx))?;
         Pin::new(&mut self.io).poll_shutdown(cx)
     }
 
@@ -345,6 +388,27 @@ mod tests {
         upgraded.downcast::<Mock>().unwrap();
     }
 
+    #[tokio::test]
+    async fn upgraded_write_buf() {
+        use tokio::io::AsyncWriteExt;
+
+        let upgraded = Upgraded::new(std::io::Cursor::new(Vec::new()), Bytes::new())
+            .with_write_buf(Bytes::from_static(b"queued"));
+        assert_eq!(upgraded.write_buf(), b"queued");
+
+        // the queued bytes are recoverable from the parts
+        let parts = upgraded.downcast::<std::io::Cursor<Vec<u8>>>().unwrap();
+        assert_eq!(parts.write_buf, "queued");
+
+        // and are written out before anything else
+        let mut upgraded = Upgraded::new(parts.io, parts.read_buf).with_write_buf(parts.write_buf);
+        upgraded.write_all(b" then new").await.unwrap();
+        assert!(upgraded.write_buf().is_empty());
+
+        let parts = upgraded.downcast::<std::io::Cursor<Vec<u8>>>().unwrap();
+        assert_eq!(parts.io.into_inner(), b"queued then new");
+    }
+
     // TODO: replace with tokio_test::io when it can test write_buf
     struct Mock;
 
@@ -682,6 +682,30 @@ test! {
             body: None,
 }
 
+test! {
+    name: client_head_ignores_declared_content_length,
+
+    server:
+        expected: "HEAD /head HTTP/1.1\r\nhost: {addr}\r\n\r\n",
+        reply: "\
+            HTTP/1.1 200 OK\r\n\
+            content-length: 100\r\n\
+            \r\n\
+            ",
+
+    client:
+        request: {
+            method: HEAD,
+            url: "http://{addr}/head",
+        },
+        response:
+            status: OK,
+            headers: {
+                "content-length" => "100",
+            },
+            body: None,
+}
+
 test! {
     name: client_response_transfer_encoding_not_chunked,
 
@@ -1109,7 +1133,7 @@ test! {
 mod dispatch_impl {
     use super::*;
     use std::io::{self, Read, Write};
-    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
     use std::sync::Arc;
     use std::thread;
     use std::time::Duration;
@@ -1237,6 +1261,40 @@ mod dispatch_impl {
         rt.block_on(closes.into_future()).0.expect("closes");
     }
 
+    #[tokio::test]
+    async fn client_post() {
+        let _ = pretty_env_logger::try_init();
+
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let mut sock = server.accept().unwrap().0;
+            sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            sock.set_write_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            let expected = "POST /a HTTP/1.1\r\nhost: ";
+            let mut buf = vec![0; 4096];
+            let mut n = 0;
+            while !s(&buf[..n]).ends_with("hello") {
+                let read = sock.read(&mut buf[n..]).expect("read 1");
+                assert_ne!(read, 0, "unexpected eof");
+                n += read;
+            }
+            let req = s(&buf[..n]);
+            assert!(req.starts_with(expected), "{:?}", req);
+            assert!(req.contains("content-length: 5\r\n"), "{:?}", req);
+            assert!(req.ends_with("\r\n\r\nhello"), "{:?}", req);
+            sock.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .expect("write 1");
+        });
+
+        let client = Client::new();
+        let uri = format!("http://{}/a", addr).parse().unwrap();
+        let res = client.post(uri, Body::from("hello")).await.unwrap();
+        assert_eq!(res.status(), hyper::StatusCode::OK);
+    }
+
     #[tokio::test]
     async fn drop_client_closes_idle_connections() {
         use futures_util::future;
@@ -1533,6 +1591,40 @@ mod dispatch_impl {
         assert_eq!(connects.load(Ordering::Relaxed), 0);
     }
 
+    #[test]
+    fn proxy_mode_sends_absolute_form() {
+        let _ = pretty_env_logger::try_init();
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let rt = support::runtime();
+
+        let client = Client::new();
+
+        let (tx1, rx1) = oneshot::channel();
+        thread::spawn(move || {
+            let mut sock = server.accept().unwrap().0;
+            sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            sock.set_write_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            let mut buf = [0; 4096];
+            let n = sock.read(&mut buf).expect("read 1");
+            let expected = format!("GET http://{}/a?b HTTP/1.1\r\n", addr);
+            assert_eq!(s(&buf[..n.min(expected.len())]), expected);
+            sock.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .expect("write 1");
+            let _ = tx1.send(());
+        });
+
+        let rx = rx1.expect("thread panicked");
+        let mut req = Request::builder()
+            .uri(&*format!("http://{}/a?b", addr))
+            .body(Body::empty())
+            .unwrap();
+        req.extensions_mut().insert(hyper::ext::ProxyMode);
+        let res = client.request(req);
+        rt.block_on(future::join(res, rx).map(|r| r.0)).unwrap();
+    }
+
     #[test]
     fn client_keep_alive_0() {
         let _ = pretty_env_logger::try_init();
@@ -2033,11 +2125,135 @@ mod dispatch_impl {
         drop(client);
     }
 
+    #[tokio::test]
+    async fn http2_keep_alive_evicts_unresponsive_pooled_connection() {
+        use hyper::server::conn::Http;
+        use hyper::service::service_fn;
+        use hyper::Response;
+        use tokio::net::TcpListener;
+
+        let _ = pretty_env_logger::try_init();
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connector = DebugConnector::new();
+        let connects = connector.connects.clone();
+
+        let client = Client::builder()
+            .http2_only(true)
+            .http2_keep_alive_interval(Duration::from_millis(100))
+            .http2_keep_alive_timeout(Duration::from_millis(100))
+            // the pooled connection is idle while we wait
+            .http2_keep_alive_while_idle(true)
+            .build::<_, Body>(connector);
+
+        tokio::spawn(async move {
+            // The first connection answers one request, and then is never
+            // polled again, so the client's pings go unacknowledged.
+            let (sock, _) = listener.accept().await.expect("accept 1");
+            let mut conn = h2::server::handshake(sock).await.expect("handshake 1");
+            let (_req, mut respond) = conn.accept().await.expect("stream 1").expect("req 1");
+            respond
+                .send_response(Response::new(()), true)
+                .expect("send_response 1");
+            // drive the connection just long enough to flush the response
+            let _ = tokio::time::timeout(Duration::from_millis(100), conn.accept()).await;
+
+            let (sock, _) = listener.accept().await.expect("accept 2");
+            Http::new()
+                .http2_only(true)
+                .serve_connection(
+                    sock,
+                    service_fn(
+                        |_| async move { Ok::<_, hyper::Error>(Response::new(Body::empty())) },
+                    ),
+                )
+                .await
+                .expect("server 2");
+            drop(conn);
+        });
+
+        let url = format!("http://{}/a", addr)
+            .parse::<::hyper::Uri>()
+            .unwrap();
+        client.get(url.clone()).await.expect("res 1");
+        assert_eq!(connects.load(Ordering::SeqCst), 1);
+
+        // sleep longer than the keep-alive interval and timeout combined
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        client.get(url).await.expect("res 2");
+        assert_eq!(
+            connects.load(Ordering::SeqCst),
+            2,
+            "dead pooled connection should not be reused"
+        );
+    }
+
+    #[tokio::test]
+    async fn retry_canceled_requests_only_if_idempotent() {
+        use hyper::server::conn::Http;
+        use hyper::service::service_fn;
+        use hyper::Response;
+        use tokio::net::TcpListener;
+
+        let _ = pretty_env_logger::try_init();
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connector = DebugConnector::new();
+        let connects = connector.connects.clone();
+        let stale = connector.stale.clone();
+
+        let client = Client::builder().build::<_, Body>(connector);
+
+        tokio::spawn(async move {
+            loop {
+                let (sock, _) = listener.accept().await.expect("accept");
+                tokio::spawn(Http::new().serve_connection(
+                    sock,
+                    service_fn(
+                        |_| async move { Ok::<_, hyper::Error>(Response::new(Body::empty())) },
+                    ),
+                ));
+            }
+        });
+
+        let url = format!("http://{}/a", addr)
+            .parse::<::hyper::Uri>()
+            .unwrap();
+        client.get(url.clone()).await.expect("res 1");
+        assert_eq!(connects.load(Ordering::SeqCst), 1);
+        // let the connection go back to the pool
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The pooled connection turns out to be closed when the GET is
+        // sent on it, so it's retried on a new connection.
+        stale.store(true, Ordering::SeqCst);
+        client.get(url.clone()).await.expect("GET is retried");
+        assert_eq!(connects.load(Ordering::SeqCst), 2);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // A POST in the same situation isn't.
+        stale.store(true, Ordering::SeqCst);
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(url)
+            .body(Body::empty())
+            .unwrap();
+        let err = client.request(req).await.expect_err("POST is not retried");
+        assert!(err.is_canceled(), "{:?}", err);
+        assert_eq!(connects.load(Ordering::SeqCst), 2);
+    }
+
     #[derive(Clone)]
     struct DebugConnector {
         http: HttpConnector,
         closes: mpsc::Sender<()>,
         connects: Arc<AtomicUsize>,
+        stale: Arc<AtomicBool>,
         is_proxy: bool,
         alpn_h2: bool,
     }
@@ -2054,6 +2270,7 @@ mod dispatch_impl {
                 http,
                 closes,
                 connects: Arc::new(AtomicUsize::new(0)),
+                stale: Arc::new(AtomicBool::new(false)),
                 is_proxy: false,
                 alpn_h2: false,
             }
@@ -2078,11 +2295,13 @@ mod dispatch_impl {
         fn call(&mut self, dst: Uri) -> Self::Future {
             self.connects.fetch_add(1, Ordering::SeqCst);
             let closes = self.closes.clone();
+            let stale = self.stale.clone();
             let is_proxy = self.is_proxy;
             let is_alpn_h2 = self.alpn_h2;
             Box::pin(self.http.call(dst).map_ok(move |tcp| DebugStream {
                 tcp,
                 on_drop: closes,
+                stale,
                 is_alpn_h2,
                 is_proxy,
             }))
@@ -2092,6 +2311,9 @@ mod dispatch_impl {
     struct DebugStream {
         tcp: TcpStream,
         on_drop: mpsc::Sender<()>,
+        // When set, the next read on any stream sees EOF, as if the server
+        // had closed an idle connection.
+        stale: Arc<AtomicBool>,
         is_alpn_h2: bool,
         is_proxy: bool,
     }
@@ -2132,6 +2354,9 @@ mod dispatch_impl {
             cx: &mut Context<'_>,
             buf: &mut ReadBuf<'_>,
         ) -> Poll<io::Result<()>> {
+            if self.stale.swap(false, Ordering::SeqCst) {
+                return Poll::Ready(Ok(()));
+            }
             Pin::new(&mut self.tcp).poll_read(cx, buf)
         }
     }
@@ -2157,7 +2382,7 @@ mod conn {
     use std::thread;
     use std::time::Duration;
 
-    use bytes::Buf;
+    use bytes::{Buf, Bytes};
     use futures_channel::oneshot;
     use futures_util::future::{self, poll_fn, FutureExt, TryFutureExt};
     use futures_util::StreamExt;
@@ -2214,6 +2439,161 @@ mod conn {
         future::join(server, client).await;
     }
 
+    #[tokio::test]
+    async fn http1_handshake_reports_version() {
+        let _ = ::pretty_env_logger::try_init();
+        let listener = TkTcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let _sock = listener.accept().await.unwrap().0;
+        });
+
+        let tcp = tcp_connect(&addr).await.expect("connect");
+        let (client, _conn) = conn::handshake(tcp).await.expect("handshake");
+        assert_eq!(client.http_version(), hyper::Version::HTTP_11);
+    }
+
+    #[tokio::test]
+    async fn http1_handshake_with_read_buf() {
+        let _ = ::pretty_env_logger::try_init();
+        let listener = TkTcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let mut sock = listener.accept().await.unwrap().0;
+            let mut buf = [0; 4096];
+            let n = sock.read(&mut buf).await.expect("read");
+            assert_eq!(s(&buf[..n]), "GET /a HTTP/1.1\r\n\r\n");
+            // The status line was already read by the client.
+            sock.write_all(b"Content-Length: 5\r\n\r\nhello")
+                .await
+                .unwrap();
+            let _ = drain_til_eof(sock).await;
+        });
+
+        let tcp = tcp_connect(&addr).await.expect("connect");
+        let (mut client, conn) = conn::Builder::new()
+            .handshake_with_read_buf::<_, Body>(tcp, Bytes::from_static(b"HTTP/1.1 200 OK\r\n"))
+            .await
+            .expect("handshake");
+        tokio::spawn(async move {
+            conn.await.expect("http conn");
+        });
+
+        let req = Request::builder().uri("/a").body(Body::empty()).unwrap();
+        let res = client.send_request(req).await.expect("send_request");
+        assert_eq!(res.status(), hyper::StatusCode::OK);
+        let body = concat(res.into_body()).await.expect("body");
+        assert_eq!(body, "hello");
+    }
+
+    #[tokio::test]
+    async fn http1_poll_flush_writes_queued_request() {
+        let _ = ::pretty_env_logger::try_init();
+        let (io, mut server) = tokio::io::duplex(4096);
+
+        let (mut client, mut conn) = conn::handshake(io).await.expect("handshake");
+
+        let req = Request::builder().uri("/a").body(Body::empty()).unwrap();
+        let res = client.send_request(req);
+
+        // nothing is written until the connection is polled
+        let mut buf = [0; 4096];
+        assert!(server.read(&mut buf).now_or_never().is_none());
+
+        poll_fn(|cx| conn.poll_flush(cx)).await.expect("poll_flush");
+        let n = server.read(&mut buf).await.expect("read");
+        assert_eq!(s(&buf[..n]), "GET /a HTTP/1.1\r\n\r\n");
+
+        server
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .await
+            .unwrap();
+        drop(client);
+        let (res, conn) = future::join(res, conn).await;
+        assert_eq!(res.expect("response").status(), StatusCode::OK);
+        conn.expect("http conn");
+    }
+
+    #[tokio::test]
+    async fn http1_max_buf_size_rejects_oversized_response_head() {
+        let _ = ::pretty_env_logger::try_init();
+        let listener = TkTcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let mut sock = listener.accept().await.unwrap().0;
+            let mut buf = [0; 4096];
+            let n = sock.read(&mut buf).await.expect("read");
+            assert_ne!(n, 0);
+            let mut res = b"HTTP/1.1 200 OK\r\nx-big: ".to_vec();
+            res.resize(res.len() + 16 * 1024, b'a');
+            let _ = sock.write_all(&res).await;
+            let _ = drain_til_eof(sock).await;
+        });
+
+        let tcp = tcp_connect(&addr).await.expect("connect");
+        let (mut client, conn) = conn::Builder::new()
+            .http1_max_buf_size(8192)
+            .handshake::<_, Body>(tcp)
+            .await
+            .expect("handshake");
+        tokio::spawn(async move {
+            let _ = conn.await;
+        });
+
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let err = client.send_request(req).await.expect_err("send_request");
+        assert!(err.is_parse_too_large(), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn http1_response_keeps_custom_reason_phrase() {
+        use hyper::ext::ReasonPhrase;
+
+        let _ = ::pretty_env_logger::try_init();
+        let listener = TkTcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let mut sock = listener.accept().await.unwrap().0;
+            let mut buf = [0; 4096];
+            for reason in &["Totally Fine", "OK"] {
+                let n = sock.read(&mut buf).await.expect("read");
+                assert_ne!(n, 0);
+                let res = format!("HTTP/1.1 200 {}\r\nContent-Length: 0\r\n\r\n", reason);
+                sock.write_all(res.as_bytes()).await.expect("write");
+            }
+        });
+
+        let tcp = tcp_connect(&addr).await.expect("connect");
+        let (mut client, conn) = conn::handshake(tcp).await.expect("handshake");
+        tokio::spawn(async move {
+            conn.await.expect("http conn");
+        });
+
+        let req = || Request::builder().uri("/").body(Body::empty()).unwrap();
+
+        let res = client.send_request(req()).await.expect("send_request");
+        assert_eq!(res.status(), StatusCode::OK);
+        let reason = res.extensions().get::<ReasonPhrase>().expect("reason");
+        assert_eq!(reason.as_bytes(), b"Totally Fine");
+        assert_eq!(reason.to_str(), Some("Totally Fine"));
+
+        // canonical reasons aren't stored
+        let res = client.send_request(req()).await.expect("send_request");
+        assert!(res.extensions().get::<ReasonPhrase>().is_none());
+    }
+
     #[test]
     fn incoming_content_length() {
         use hyper::body::HttpBody;
@@ -2311,6 +2691,62 @@ mod conn {
         sender.join().expect("sender thread panicked");
     }
 
+    #[tokio::test]
+    async fn http1_chunked_request_trailers() {
+        let _ = ::pretty_env_logger::try_init();
+        let listener = TkTcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = async move {
+            let mut sock = listener.accept().await.unwrap().0;
+            let expected = "\
+                POST / HTTP/1.1\r\n\
+                transfer-encoding: chunked\r\n\
+                \r\n\
+                5\r\nhello\r\n\
+                0\r\n\
+                chunky-trailer: header data\r\n\
+                \r\n\
+            ";
+            let mut buf = vec![0; expected.len()];
+            sock.read_exact(&mut buf).await.expect("read 1");
+            assert_eq!(s(&buf), expected);
+
+            sock.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+        };
+
+        let client = async move {
+            let tcp = tcp_connect(&addr).await.expect("connect");
+            let (mut client, conn) = conn::handshake(tcp).await.expect("handshake");
+
+            tokio::task::spawn(async move {
+                conn.await.expect("http conn");
+            });
+
+            let (mut sender, body) = Body::channel();
+            tokio::task::spawn(async move {
+                sender.send_data("hello".into()).await.expect("send_data");
+                let mut trailers = http::HeaderMap::new();
+                trailers.insert("chunky-trailer", "header data".parse().unwrap());
+                sender.send_trailers(trailers).await.expect("send_trailers");
+            });
+
+            let req = Request::builder()
+                .method(Method::POST)
+                .uri("/")
+                .body(body)
+                .unwrap();
+            let res = client.send_request(req).await.expect("send_request");
+            assert_eq!(res.status(), hyper::StatusCode::OK);
+        };
+
+        future::join(server, client).await;
+    }
+
     #[test]
     fn uri_absolute_form() {
         let server = TcpListener::bind("127.0.0.1:0").unwrap();
@@ -2635,6 +3071,55 @@ mod conn {
         assert_eq!(vec, b"bar=foo");
     }
 
+    #[tokio::test]
+    async fn http2_max_header_list_size() {
+        use hyper::service::{make_service_fn, service_fn};
+        use hyper::Server;
+
+        let _ = pretty_env_logger::try_init();
+
+        let server = Server::bind(&([127, 0, 0, 1], 0).into())
+            .http2_only(true)
+            .serve(make_service_fn(|_| async move {
+                Ok::<_, hyper::Error>(service_fn(|_req| async move {
+                    let res = Response::builder()
+                        .header("x-large", "a".repeat(2048))
+                        .body(Body::empty())
+                        .unwrap();
+                    Ok::<_, hyper::Error>(res)
+                }))
+            }));
+        let addr = server.local_addr();
+        tokio::task::spawn(async move {
+            server.await.expect("server");
+        });
+
+        let get = |max| async move {
+            let io = tcp_connect(&addr).await.expect("tcp connect");
+            let (mut client, conn) = conn::Builder::new()
+                .http2_only(true)
+                .http2_max_header_list_size(max)
+                .handshake::<_, Body>(io)
+                .await
+                .expect("http handshake");
+            tokio::task::spawn(async move {
+                let _ = conn.await;
+            });
+
+            let req = Request::builder()
+                .uri(format!("http://{}/", addr))
+                .body(Body::empty())
+                .expect("request builder");
+            client.send_request(req).await
+        };
+
+        // The default is too large to exceed in a test, so start below the
+        // size of the response headers, and then raise it.
+        get(1024).await.expect_err("headers over the limit");
+        let res = get(4096).await.expect("headers under the limit");
+        assert_eq!(res.headers()["x-large"].len(), 2048);
+    }
+
     #[tokio::test]
     async fn http2_detect_conn_eof() {
         use futures_util::future;
@@ -2882,6 +3367,58 @@ mod conn {
             .expect("client should be open");
     }
 
+    #[tokio::test]
+    async fn http2_initial_stream_window_size() {
+        let _ = pretty_env_logger::try_init();
+
+        let listener = TkTcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let sock = listener.accept().await.unwrap().0;
+            let mut h2 = h2::server::handshake(sock).await.unwrap();
+
+            let (_req, mut respond) = h2.accept().await.unwrap().unwrap();
+            tokio::spawn(async move {
+                poll_fn(|cx| h2.poll_closed(cx)).await.unwrap();
+            });
+
+            let mut send_stream = respond.send_response(Response::default(), false).unwrap();
+
+            // The client's SETTINGS limit how much we may send on this stream.
+            send_stream.reserve_capacity(1_000);
+            let capacity = poll_fn(|cx| send_stream.poll_capacity(cx))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(capacity, 100);
+
+            send_stream.send_data(vec![b'x'; 100].into(), true).unwrap();
+        });
+
+        let io = tcp_connect(&addr).await.expect("tcp connect");
+        let (mut client, conn) = conn::Builder::new()
+            .http2_only(true)
+            .http2_initial_stream_window_size(100)
+            .http2_initial_connection_window_size(1_000_000)
+            .handshake::<_, Body>(io)
+            .await
+            .expect("http handshake");
+
+        tokio::spawn(async move {
+            conn.await.expect("client conn shouldn't error");
+        });
+
+        let req = Request::get("/").body(hyper::Body::empty()).unwrap();
+        let res = client.send_request(req).await.expect("send_request");
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = concat(res.into_body()).await.unwrap();
+        assert_eq!(body.len(), 100);
+    }
+
     #[tokio::test]
     async fn h2_connect() {
         let _ = pretty_env_logger::try_init();
@@ -6,7 +6,7 @@ use std::io::{self, Read, Write};
 use std::net::TcpListener as StdTcpListener;
 use std::net::{Shutdown, SocketAddr, TcpStream};
 use std::pin::Pin;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
@@ -632,6 +632,261 @@ fn response_does_not_set_chunked_if_body_not_allowed() {
     assert_eq!(lines.next(), None);
 }
 
+#[tokio::test]
+async fn empty_body_response_uses_content_length() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut req = connect(&addr);
+        req.write_all(
+            b"\
+            GET / HTTP/1.1\r\n\
+            Host: example.domain\r\n\
+            Connection: close\r\n\
+            \r\n\
+        ",
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        req.read_to_string(&mut response).unwrap();
+
+        assert!(
+            !has_header(&response, "transfer-encoding:"),
+            "{:?}",
+            response
+        );
+        assert!(
+            has_header(&response, "content-length: 0\r\n"),
+            "{:?}",
+            response
+        );
+        assert!(response.ends_with("\r\n\r\n"), "{:?}", response);
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .serve_connection(
+            socket,
+            service_fn(|_| future::ok::<_, hyper::Error>(Response::new(Body::empty()))),
+        )
+        .await
+        .expect("serve_connection");
+
+    child.join().expect("client thread");
+}
+
+#[tokio::test]
+async fn chunked_response_trailers_declared_by_trailer_header() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut req = connect(&addr);
+        req.write_all(
+            b"\
+            GET / HTTP/1.1\r\n\
+            Host: example.domain\r\n\
+            TE: trailers\r\n\
+            Connection: close\r\n\
+            \r\n\
+        ",
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        req.read_to_string(&mut response).unwrap();
+
+        let (head, body) = response.split_at(response.find("\r\n\r\n").unwrap() + 4);
+        assert!(
+            has_header(head, "transfer-encoding: chunked\r\n"),
+            "{:?}",
+            head
+        );
+        assert!(has_header(head, "trailer: expires\r\n"), "{:?}", head);
+        assert_eq!(
+            body,
+            "5\r\nhello\r\n0\r\nexpires: Wed, 21 Oct 2015 07:28:00 GMT\r\n\r\n"
+        );
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .serve_connection(
+            socket,
+            service_fn(|_| {
+                let (mut tx, body) = Body::channel();
+                tokio::spawn(async move {
+                    tx.send_data("hello".into()).await.expect("send_data");
+                    let mut trailers = http::HeaderMap::new();
+                    trailers.insert("expires", "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap());
+                    tx.send_trailers(trailers).await.expect("send_trailers");
+                });
+                let res = Response::builder()
+                    .header("trailer", "expires")
+                    .body(body)
+                    .unwrap();
+                future::ok::<_, hyper::Error>(res)
+            }),
+        )
+        .await
+        .expect("serve_connection");
+
+    child.join().expect("client thread");
+}
+
+#[tokio::test]
+async fn chunked_response_trailers_not_sent_without_te() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut req = connect(&addr);
+        req.write_all(
+            b"\
+            GET / HTTP/1.1\r\n\
+            Host: example.domain\r\n\
+            Connection: close\r\n\
+            \r\n\
+        ",
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        req.read_to_string(&mut response).unwrap();
+
+        let (_, body) = response.split_at(response.find("\r\n\r\n").unwrap() + 4);
+        assert_eq!(body, "5\r\nhello\r\n0\r\n\r\n");
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .serve_connection(
+            socket,
+            service_fn(|_| {
+                let (mut tx, body) = Body::channel();
+                tokio::spawn(async move {
+                    tx.send_data("hello".into()).await.expect("send_data");
+                    let mut trailers = http::HeaderMap::new();
+                    trailers.insert("x-checksum", "abc123".parse().unwrap());
+                    tx.send_trailers(trailers).await.expect("send_trailers");
+                });
+                future::ok::<_, hyper::Error>(Response::new(body))
+            }),
+        )
+        .await
+        .expect("serve_connection");
+
+    child.join().expect("client thread");
+}
+
+#[tokio::test]
+async fn chunked_response_trailers_title_case() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut req = connect(&addr);
+        req.write_all(
+            b"\
+            GET / HTTP/1.1\r\n\
+            Host: example.domain\r\n\
+            TE: trailers\r\n\
+            Connection: close\r\n\
+            \r\n\
+        ",
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        req.read_to_string(&mut response).unwrap();
+
+        let (_, body) = response.split_at(response.find("\r\n\r\n").unwrap() + 4);
+        assert_eq!(body, "5\r\nhello\r\n0\r\nX-Checksum: abc123\r\n\r\n");
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .http1_title_case_headers(true)
+        .serve_connection(
+            socket,
+            service_fn(|_| {
+                let (mut tx, body) = Body::channel();
+                tokio::spawn(async move {
+                    tx.send_data("hello".into()).await.expect("send_data");
+                    let mut trailers = http::HeaderMap::new();
+                    trailers.insert("x-checksum", "abc123".parse().unwrap());
+                    tx.send_trailers(trailers).await.expect("send_trailers");
+                });
+                future::ok::<_, hyper::Error>(Response::new(body))
+            }),
+        )
+        .await
+        .expect("serve_connection");
+
+    child.join().expect("client thread");
+}
+
+#[tokio::test]
+async fn chunked_request_trailers() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut req = connect(&addr);
+        req.write_all(
+            b"\
+            POST / HTTP/1.1\r\n\
+            Host: example.domain\r\n\
+            Transfer-Encoding: chunked\r\n\
+            Connection: close\r\n\
+            \r\n\
+            5\r\nhello\r\n\
+            0\r\n\
+            X-Checksum: abc123\r\n\
+            \r\n\
+        ",
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        req.read_to_string(&mut response).unwrap();
+        assert!(
+            response.starts_with("HTTP/1.1 200 OK\r\n"),
+            "{:?}",
+            response
+        );
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .serve_connection(
+            socket,
+            service_fn(|req: Request<Body>| async move {
+                let mut body = req.into_body();
+                let mut data = Vec::new();
+                while let Some(chunk) = body.data().await {
+                    data.extend_from_slice(&chunk?);
+                }
+                assert_eq!(data, b"hello");
+
+                let trailers = body.trailers().await?.expect("trailers");
+                assert_eq!(trailers["x-checksum"], "abc123");
+                Ok::<_, hyper::Error>(Response::new(Body::empty()))
+            }),
+        )
+        .await
+        .expect("serve_connection");
+
+    child.join().expect("client thread");
+}
+
 #[test]
 fn keep_alive() {
     let foo_bar = b"foo bar baz";
@@ -759,6 +1014,47 @@ fn http_10_close_on_no_ka() {
     );
 }
 
+#[tokio::test]
+async fn http_10_request_version() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            GET / HTTP/1.0\r\n\
+            Host: example.domain\r\n\
+            Connection: keep-alive\r\n\
+            \r\n\
+            GET / HTTP/1.1\r\n\
+            Host: example.domain\r\n\
+            Connection: close\r\n\
+            \r\n\
+        ",
+        )
+        .expect("write 1");
+        let mut buf = Vec::new();
+        tcp.read_to_end(&mut buf).expect("read 1");
+    });
+
+    let (versions_tx, versions_rx) = mpsc::channel();
+    let svc = service_fn(move |req: Request<Body>| {
+        versions_tx.send(req.version()).unwrap();
+        future::ok::<_, hyper::Error>(Response::new(Body::empty()))
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .serve_connection(socket, svc)
+        .await
+        .expect("serve_connection");
+
+    assert_eq!(versions_rx.recv().unwrap(), Version::HTTP_10);
+    assert_eq!(versions_rx.recv().unwrap(), Version::HTTP_11);
+}
+
 #[test]
 fn disable_keep_alive() {
     let foo_bar = b"foo bar baz";
@@ -960,6 +1256,79 @@ async fn expect_continue_waits_for_body_poll() {
     child.join().expect("client thread");
 }
 
+#[tokio::test]
+async fn expect_continue_not_sent_when_body_dropped_before_response() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut tcp = connect(&addr);
+
+        let mut req = b"\
+            POST /foo HTTP/1.1\r\n\
+            Host: example.domain\r\n\
+            Expect: 100-continue\r\n\
+            Content-Length: 100\r\n\
+            \r\n\
+        "
+        .to_vec();
+        // the client doesn't wait for 100 Continue before sending the body
+        req.extend_from_slice(&[b'x'; 100]);
+        tcp.write_all(&req).expect("write 1");
+
+        let mut resp = Vec::new();
+        let mut buf = [0; 256];
+        while !resp.ends_with(b"\r\n\r\n") {
+            let n = tcp.read(&mut buf).expect("read 1");
+            assert_ne!(n, 0, "connection closed after first response");
+            resp.extend_from_slice(&buf[..n]);
+        }
+
+        // the connection is still usable for another request
+        tcp.write_all(
+            b"\
+            GET /bar HTTP/1.1\r\n\
+            Host: example.domain\r\n\
+            Connection: close\r\n\
+            \r\n\
+        ",
+        )
+        .expect("write 2");
+        tcp.read_to_end(&mut resp).expect("read 2");
+        String::from_utf8(resp).unwrap()
+    });
+
+    let (socket, _) = listener.accept().await.expect("accept");
+
+    Http::new()
+        .serve_connection(
+            socket,
+            service_fn(|req| {
+                // Reject based on the headers alone, dropping the body
+                // before the response is ready.
+                drop(req);
+                tokio::time::sleep(Duration::from_millis(50)).map(|_| {
+                    Response::builder()
+                        .status(StatusCode::FORBIDDEN)
+                        .body(hyper::Body::empty())
+                })
+            }),
+        )
+        .await
+        .expect("serve_connection");
+
+    let resp = child.join().expect("client thread");
+    assert!(resp.starts_with("HTTP/1.1 403 Forbidden\r\n"), "{:?}", resp);
+    assert_eq!(
+        resp.matches("HTTP/1.1 403 Forbidden\r\n").count(),
+        2,
+        "{:?}",
+        resp
+    );
+    assert!(!resp.contains("100 Continue"), "{:?}", resp);
+}
+
 #[test]
 fn pipeline_disabled() {
     let server = serve();
@@ -1058,18 +1427,101 @@ fn pipeline_enabled() {
     assert_eq!(n, 0);
 }
 
-#[test]
-fn http_10_request_receives_http_10_response() {
-    let server = serve();
+#[tokio::test]
+async fn write_flush_delay_coalesces_body_chunks() {
+    let _ = pretty_env_logger::try_init();
+    tokio::time::pause();
 
-    let mut req = connect(server.addr());
-    req.write_all(
-        b"\
-        GET / HTTP/1.0\r\n\
-        \r\n\
-    ",
-    )
-    .unwrap();
+    let (mut client, server) = tokio::io::duplex(4096);
+    let writes = Arc::new(AtomicUsize::new(0));
+    let socket = WriteCounter {
+        stream: server,
+        writes: writes.clone(),
+    };
+
+    let (mut body_tx, body) = Body::channel();
+    let mut body = Some(body);
+    let svc = service_fn(move |_req: Request<Body>| {
+        let body = body.take().expect("only one request");
+        future::ok::<_, hyper::Error>(Response::new(body))
+    });
+
+    tokio::spawn(async move {
+        Http::new()
+            .http1_writev(false)
+            .write_flush_delay(Some(Duration::from_millis(10)))
+            .serve_connection(socket, svc)
+            .await
+            .expect("serve_connection");
+    });
+
+    let start = tokio::time::Instant::now();
+    client
+        .write_all(b"GET / HTTP/1.1\r\nHost: example.domain\r\n\r\n")
+        .await
+        .unwrap();
+    body_tx.send_data("foo".into()).await.unwrap();
+    body_tx.send_data("bar".into()).await.unwrap();
+
+    // the body isn't finished, so nothing is flushed until the delay passes
+    let mut buf = vec![0; 4096];
+    let n = client.read(&mut buf).await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(10));
+
+    let response = s(&buf[..n]);
+    assert!(
+        response.starts_with("HTTP/1.1 200 OK\r\n"),
+        "{:?}",
+        response
+    );
+    assert!(
+        response.ends_with("\r\n\r\n3\r\nfoo\r\n3\r\nbar\r\n"),
+        "{:?}",
+        response
+    );
+    assert_eq!(writes.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn write_timeout_closes_stalled_connection() {
+    let _ = pretty_env_logger::try_init();
+    tokio::time::pause();
+
+    // the client never reads, so the server can't write more than this
+    let (mut client, socket) = tokio::io::duplex(64);
+    client
+        .write_all(b"GET / HTTP/1.1\r\nHost: example.domain\r\n\r\n")
+        .await
+        .unwrap();
+
+    let start = tokio::time::Instant::now();
+    let err = Http::new()
+        .write_timeout(Duration::from_secs(1))
+        .serve_connection(
+            socket,
+            service_fn(|_| {
+                future::ok::<_, hyper::Error>(Response::new(Body::from(vec![b'x'; 4096])))
+            }),
+        )
+        .await
+        .expect_err("serve_connection should time out");
+    assert!(err.is_timeout(), "{:?}", err);
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    drop(client);
+}
+
+#[test]
+fn http_10_request_receives_http_10_response() {
+    let server = serve();
+
+    let mut req = connect(server.addr());
+    req.write_all(
+        b"\
+        GET / HTTP/1.0\r\n\
+        \r\n\
+    ",
+    )
+    .unwrap();
 
     let expected = "HTTP/1.0 200 OK\r\ncontent-length: 0\r\n";
     let mut buf = [0; 256];
@@ -1498,111 +1950,1339 @@ async fn upgrades() {
         }),
     );
 
-    let parts = conn.without_shutdown().await.unwrap();
-    assert_eq!(parts.read_buf, "eagerly optimistic");
+    let parts = conn.without_shutdown().await.unwrap();
+    assert_eq!(parts.read_buf, "eagerly optimistic");
+
+    // wait so that we don't write until other side saw 101 response
+    rx.await.unwrap();
+
+    let mut io = parts.io;
+    io.write_all(b"foo=bar").await.unwrap();
+    let mut vec = vec![];
+    io.read_to_end(&mut vec).await.unwrap();
+    assert_eq!(vec, b"bar=foo");
+}
+
+#[tokio::test]
+async fn upgrades_keep_unflushed_response_bytes() {
+    let _ = pretty_env_logger::try_init();
+
+    // the client doesn't read until after the upgrade, so most of the 101
+    // response is still buffered when the connection is handed off
+    let (mut client, socket) = tokio::io::duplex(16);
+
+    let (upgraded_tx, upgraded_rx) = oneshot::channel();
+    let upgraded_tx = Arc::new(Mutex::new(Some(upgraded_tx)));
+    let svc = service_fn(move |req: Request<Body>| {
+        let upgraded_tx = upgraded_tx.lock().unwrap().take().expect("one request");
+        tokio::spawn(async move {
+            let upgraded = hyper::upgrade::on(req).await.expect("on_upgrade");
+            let _ = upgraded_tx.send(upgraded);
+        });
+        let res = Response::builder()
+            .status(101)
+            .header("upgrade", "foobar")
+            .body(hyper::Body::empty())
+            .unwrap();
+        future::ok::<_, hyper::Error>(res)
+    });
+    tokio::spawn(async move {
+        Http::new()
+            .serve_connection(socket, svc)
+            .with_upgrades()
+            .await
+            .expect("serve_connection");
+    });
+
+    client
+        .write_all(b"GET / HTTP/1.1\r\nUpgrade: foobar\r\nConnection: upgrade\r\n\r\n")
+        .await
+        .unwrap();
+
+    let mut upgraded = upgraded_rx.await.expect("upgraded");
+    let pending = upgraded.write_buf().to_vec();
+    assert!(!pending.is_empty(), "response should not be fully flushed");
+    tokio::spawn(async move {
+        upgraded.write_all(b"foo=bar").await.unwrap();
+        upgraded.shutdown().await.unwrap();
+    });
+
+    let mut response = Vec::new();
+    client.read_to_end(&mut response).await.unwrap();
+    let response = String::from_utf8(response).unwrap();
+    assert!(
+        response.starts_with("HTTP/1.1 101 Switching Protocols\r\n"),
+        "{:?}",
+        response
+    );
+    // the buffered bytes are written before any new data
+    let expected_end = format!("{}foo=bar", s(&pending));
+    assert!(response.ends_with(&expected_end), "{:?}", response);
+    assert!(response.contains("\r\n\r\nfoo=bar"), "{:?}", response);
+}
+
+#[tokio::test]
+async fn upgrades_recover_service() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    struct Upgrader {
+        id: usize,
+        calls: usize,
+    }
+
+    impl tower_service::Service<Request<Body>> for Upgrader {
+        type Response = Response<Body>;
+        type Error = hyper::Error;
+        type Future = future::Ready<Result<Response<Body>, Self::Error>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Ok(()).into()
+        }
+
+        fn call(&mut self, _req: Request<Body>) -> Self::Future {
+            self.calls += 1;
+            let res = Response::builder()
+                .status(101)
+                .header("upgrade", "foobar")
+                .body(hyper::Body::empty())
+                .unwrap();
+            future::ok(res)
+        }
+    }
+
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            GET / HTTP/1.1\r\n\
+            Upgrade: foobar\r\n\
+            Connection: upgrade\r\n\
+            \r\n\
+            eagerly optimistic\
+        ",
+        )
+        .expect("write 1");
+        let mut buf = [0; 256];
+        tcp.read(&mut buf).expect("read 1");
+
+        let expected = "HTTP/1.1 101 Switching Protocols\r\n";
+        assert_eq!(s(&buf[..expected.len()]), expected);
+
+        let n = tcp.read(&mut buf).expect("read 2");
+        assert_eq!(s(&buf[..n]), "foo=bar");
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    let conn = Http::new().serve_connection(socket, Upgrader { id: 42, calls: 0 });
+
+    let parts = conn.without_shutdown().await.unwrap();
+    assert_eq!(parts.read_buf, "eagerly optimistic");
+    assert_eq!(parts.service.id, 42);
+    assert_eq!(parts.service.calls, 1);
+
+    let mut io = parts.io;
+    io.write_all(b"foo=bar").await.unwrap();
+    let mut vec = vec![];
+    io.read_to_end(&mut vec).await.unwrap();
+    assert!(vec.is_empty());
+}
+
+#[tokio::test]
+async fn http_connect() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = oneshot::channel();
+
+    thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            CONNECT localhost:80 HTTP/1.1\r\n\
+            \r\n\
+            eagerly optimistic\
+        ",
+        )
+        .expect("write 1");
+        let mut buf = [0; 256];
+        tcp.read(&mut buf).expect("read 1");
+
+        let expected = "HTTP/1.1 200 OK\r\n";
+        assert_eq!(s(&buf[..expected.len()]), expected);
+        let _ = tx.send(());
+
+        let n = tcp.read(&mut buf).expect("read 2");
+        assert_eq!(s(&buf[..n]), "foo=bar");
+        tcp.write_all(b"bar=foo").expect("write 2");
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    let conn = Http::new().serve_connection(
+        socket,
+        service_fn(|_| {
+            let res = Response::builder()
+                .status(200)
+                .body(hyper::Body::empty())
+                .unwrap();
+            future::ready(Ok::<_, hyper::Error>(res))
+        }),
+    );
+
+    let parts = conn.without_shutdown().await.unwrap();
+    assert_eq!(parts.read_buf, "eagerly optimistic");
+
+    // wait so that we don't write until other side saw 101 response
+    rx.await.unwrap();
+
+    let mut io = parts.io;
+    io.write_all(b"foo=bar").await.unwrap();
+    let mut vec = vec![];
+    io.read_to_end(&mut vec).await.unwrap();
+    assert_eq!(vec, b"bar=foo");
+}
+
+#[tokio::test]
+async fn http1_allow_connect_false_rejects_with_405() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            CONNECT localhost:80 HTTP/1.1\r\n\
+            \r\n\
+            eagerly optimistic\
+        ",
+        )
+        .expect("write 1");
+        let mut response = String::new();
+        tcp.read_to_string(&mut response).expect("read 1");
+
+        let expected = "HTTP/1.1 405 Method Not Allowed\r\n";
+        assert_eq!(&response[..expected.len()], expected);
+        assert!(
+            has_header(
+                &response,
+                "allow: GET, HEAD, POST, PUT, DELETE, OPTIONS, TRACE, PATCH\r\n"
+            ),
+            "{:?}",
+            response
+        );
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .http1_allow_connect(false)
+        .serve_connection(socket, unreachable_service())
+        .with_upgrades()
+        .await
+        .expect_err("rejected CONNECT should error");
+}
+
+#[tokio::test]
+async fn http1_0_no_content_length() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(b"GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n")
+            .expect("write 1");
+        // Reading to the end means the server closed the connection.
+        let mut response = String::new();
+        tcp.read_to_string(&mut response).expect("read 1");
+        response
+    });
+
+    let svc = service_fn(|_req: Request<Body>| {
+        let res = Response::builder()
+            .header("content-length", "5")
+            .body(Body::from("hello"))
+            .unwrap();
+        future::ok::<_, hyper::Error>(res)
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .http1_0_no_content_length(true)
+        .serve_connection(socket, svc)
+        .await
+        .expect("serve_connection");
+
+    let response = child.join().expect("client thread");
+    assert!(
+        response.starts_with("HTTP/1.0 200 OK\r\n"),
+        "{:?}",
+        response
+    );
+    assert!(!response.contains("content-length"), "{:?}", response);
+    assert!(!response.contains("keep-alive"), "{:?}", response);
+    assert!(response.ends_with("\r\n\r\nhello"), "{:?}", response);
+}
+
+#[tokio::test]
+async fn http1_max_uri_length() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        // The first target is exactly 16 bytes, the second is 17.
+        tcp.write_all(
+            b"\
+            GET /aaaaaaaaaaaaaaa HTTP/1.1\r\n\
+            \r\n\
+            GET /aaaaaaaaaaaaaaaa HTTP/1.1\r\n\
+            \r\n\
+        ",
+        )
+        .expect("write 1");
+        let mut response = String::new();
+        tcp.read_to_string(&mut response).expect("read 1");
+        response
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .http1_max_uri_length(16)
+        .serve_connection(socket, HelloWorld)
+        .await
+        .expect_err("target over the limit should error");
+
+    let response = child.join().expect("client thread");
+    assert!(
+        response.starts_with("HTTP/1.1 200 OK\r\n"),
+        "{:?}",
+        response
+    );
+    assert_eq!(response.matches("HTTP/1.1 ").count(), 2, "{:?}", response);
+    assert!(
+        response.contains("HTTP/1.1 414 URI Too Long\r\n"),
+        "{:?}",
+        response
+    );
+}
+
+#[tokio::test]
+async fn http1_obsolete_line_folding_rejected_by_default() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            GET / HTTP/1.1\r\n\
+            X-Folded: foo\r\n\
+            \tbar\r\n\
+            \r\n\
+        ",
+        )
+        .expect("write 1");
+        let mut response = String::new();
+        tcp.read_to_string(&mut response).expect("read 1");
+        response
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .serve_connection(socket, HelloWorld)
+        .await
+        .expect_err("folded header should error");
+
+    let response = child.join().expect("client thread");
+    assert!(
+        response.starts_with("HTTP/1.1 400 Bad Request\r\n"),
+        "{:?}",
+        response
+    );
+}
+
+#[tokio::test]
+async fn http1_allow_obsolete_line_folding() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            POST / HTTP/1.1\r\n\
+            X-Folded: foo\r\n\
+            \tbar\r\n \
+             baz\r\n\
+            Content-Length: 7\r\n\
+            Connection: close\r\n\
+            \r\n\
+            a\r\n b\r\n\
+        ",
+        )
+        .expect("write 1");
+        let mut response = String::new();
+        tcp.read_to_string(&mut response).expect("read 1");
+        response
+    });
+
+    let svc = service_fn(|req: Request<Body>| async move {
+        assert_eq!(req.headers()["x-folded"], "foo  \tbar   baz");
+        assert_eq!(req.headers()["content-length"], "7");
+        // the body isn't unfolded
+        let body = hyper::body::to_bytes(req.into_body()).await?;
+        assert_eq!(body, "a\r\n b\r\n");
+        Ok::<_, hyper::Error>(Response::new(Body::empty()))
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .http1_allow_obsolete_line_folding(true)
+        .serve_connection(socket, svc)
+        .await
+        .expect("serve_connection");
+
+    let response = child.join().expect("client thread");
+    assert!(
+        response.starts_with("HTTP/1.1 200 OK\r\n"),
+        "{:?}",
+        response
+    );
+}
+
+#[tokio::test]
+async fn http1_request_line_extra_spaces_rejected_by_default() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(b"GET  /  HTTP/1.1\r\n\r\n").expect("write 1");
+        let mut response = String::new();
+        tcp.read_to_string(&mut response).expect("read 1");
+        response
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .serve_connection(socket, HelloWorld)
+        .await
+        .expect_err("extra spaces should error");
+
+    let response = child.join().expect("client thread");
+    assert!(
+        response.starts_with("HTTP/1.1 400 Bad Request\r\n"),
+        "{:?}",
+        response
+    );
+}
+
+#[tokio::test]
+async fn http1_lenient_request_line() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(b"GET  /a  HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .expect("write 1");
+        let mut response = String::new();
+        tcp.read_to_string(&mut response).expect("read 1");
+        response
+    });
+
+    let svc = service_fn(|req: Request<Body>| async move {
+        assert_eq!(req.method(), hyper::Method::GET);
+        assert_eq!(req.uri(), "/a");
+        assert_eq!(req.version(), Version::HTTP_11);
+        Ok::<_, hyper::Error>(Response::new(Body::empty()))
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .http1_lenient_request_line(true)
+        .serve_connection(socket, svc)
+        .await
+        .expect("serve_connection");
+
+    let response = child.join().expect("client thread");
+    assert!(
+        response.starts_with("HTTP/1.1 200 OK\r\n"),
+        "{:?}",
+        response
+    );
+}
+
+#[tokio::test]
+async fn constant_service_replies_to_every_request() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            GET /healthz HTTP/1.1\r\n\
+            \r\n\
+            GET /healthz HTTP/1.1\r\n\
+            Connection: close\r\n\
+            \r\n\
+        ",
+        )
+        .expect("write 1");
+        let mut response = String::new();
+        tcp.read_to_string(&mut response).expect("read 1");
+        response
+    });
+
+    let mut headers = hyper::HeaderMap::new();
+    headers.insert("x-health", HeaderValue::from_static("good"));
+    let svc = hyper::service::constant(StatusCode::OK, headers, "ok");
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .serve_connection(socket, svc)
+        .await
+        .expect("serve_connection");
+
+    let response = child.join().expect("client thread");
+    let responses = response
+        .split("HTTP/1.1 200 OK\r\n")
+        .skip(1)
+        .collect::<Vec<_>>();
+    assert_eq!(responses.len(), 2, "{:?}", response);
+    for res in responses {
+        assert!(res.contains("x-health: good\r\n"), "{:?}", response);
+        assert!(res.ends_with("\r\n\r\nok"), "{:?}", response);
+    }
+}
+
+#[tokio::test]
+async fn http1_max_requests_per_connection() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            GET /1 HTTP/1.1\r\n\
+            \r\n\
+            GET /2 HTTP/1.1\r\n\
+            \r\n\
+        ",
+        )
+        .expect("write 1");
+        // the server closes after the second response
+        let mut response = String::new();
+        tcp.read_to_string(&mut response).expect("read 1");
+        response
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .http1_max_requests_per_connection(2)
+        .serve_connection(
+            socket,
+            service_fn(|_| future::ok::<_, hyper::Error>(Response::new(Body::empty()))),
+        )
+        .await
+        .expect("serve_connection");
+
+    let response = child.join().expect("client thread");
+    let mut responses = response.split("HTTP/1.1 200 OK\r\n").skip(1);
+    let first = responses.next().expect("first response");
+    assert!(!first.contains("connection: close"), "{:?}", response);
+    let second = responses.next().expect("second response");
+    assert!(second.contains("connection: close\r\n"), "{:?}", response);
+    assert_eq!(responses.next(), None);
+}
+
+#[tokio::test]
+async fn accept_proxy_protocol() {
+    use hyper::ext::ProxyAddrs;
+
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            PROXY TCP4 203.0.113.7 192.0.2.1 56324 80\r\n\
+            GET / HTTP/1.1\r\n\
+            Connection: close\r\n\
+            \r\n\
+        ",
+        )
+        .expect("write 1");
+        let mut response = String::new();
+        tcp.read_to_string(&mut response).expect("read 1");
+        response
+    });
+
+    let svc = service_fn(|req: Request<Body>| {
+        let addrs = req.extensions().get::<ProxyAddrs>().expect("ProxyAddrs");
+        let body = format!("{}", addrs.source());
+        future::ok::<_, hyper::Error>(Response::new(Body::from(body)))
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .accept_proxy_protocol(true)
+        .serve_connection(socket, svc)
+        .await
+        .expect("serve_connection");
+
+    let response = child.join().expect("client thread");
+    assert!(
+        response.starts_with("HTTP/1.1 200 OK\r\n"),
+        "{:?}",
+        response
+    );
+    assert!(
+        response.ends_with("\r\n\r\n203.0.113.7:56324"),
+        "{:?}",
+        response
+    );
+}
+
+#[tokio::test]
+async fn accept_proxy_protocol_malformed_header_closes() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            GET / HTTP/1.1\r\n\
+            \r\n\
+        ",
+        )
+        .expect("write 1");
+        let mut response = Vec::new();
+        tcp.read_to_end(&mut response).expect("read 1");
+        assert!(response.is_empty(), "{:?}", s(&response));
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .accept_proxy_protocol(true)
+        .serve_connection(socket, unreachable_service())
+        .await
+        .expect_err("missing PROXY header should error");
+    child.join().expect("client thread");
+}
+
+#[tokio::test]
+async fn accept_proxy_protocol_header_read_timeout() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(b"PROXY TCP4 203.0.113.7 ").expect("write 1");
+        let mut response = Vec::new();
+        tcp.read_to_end(&mut response).expect("read 1");
+        assert!(response.is_empty(), "{:?}", s(&response));
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    let conn = Http::new()
+        .accept_proxy_protocol(true)
+        .http1_header_read_timeout(Duration::from_millis(100))
+        .serve_connection(socket, unreachable_service());
+    let err = tokio::time::timeout(Duration::from_secs(5), conn)
+        .await
+        .expect("stalled PROXY header should time out")
+        .expect_err("header timeout");
+    assert_eq!(err.to_string(), "read header from client timeout");
+    child.join().expect("client thread");
+}
+
+#[tokio::test]
+async fn on_response_runs_for_every_response() {
+    async fn respond(request: &'static [u8]) -> String {
+        let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let child = thread::spawn(move || {
+            let mut tcp = connect(&addr);
+            tcp.write_all(request).expect("write 1");
+            let mut response = String::new();
+            tcp.read_to_string(&mut response).expect("read 1");
+            response
+        });
+
+        let svc = service_fn(|_req: Request<Body>| {
+            future::ok::<_, hyper::Error>(Response::new(Body::from("hello")))
+        });
+
+        let (socket, _) = listener.accept().await.unwrap();
+        let _ = Http::new()
+            .on_response(|res| {
+                res.headers_mut()
+                    .insert("x-powered-by", HeaderValue::from_static("hyper"));
+            })
+            .serve_connection(socket, svc)
+            .await;
+        child.join().expect("client thread")
+    }
+
+    let _ = pretty_env_logger::try_init();
+
+    let response = respond(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n").await;
+    assert!(
+        response.starts_with("HTTP/1.1 200 OK\r\n"),
+        "{:?}",
+        response
+    );
+    assert!(has_header(&response, "x-powered-by"), "{:?}", response);
+
+    let response = respond(b"GET / HTTP/1.1\r\nBad Header\r\n\r\n").await;
+    assert!(
+        response.starts_with("HTTP/1.1 400 Bad Request\r\n"),
+        "{:?}",
+        response
+    );
+    assert!(has_header(&response, "x-powered-by"), "{:?}", response);
+}
+
+#[tokio::test]
+async fn http2_on_response() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.expect("accept");
+
+        Http::new()
+            .http2_only(true)
+            .on_response(|res| {
+                res.headers_mut()
+                    .insert("x-powered-by", HeaderValue::from_static("hyper"));
+            })
+            .serve_connection(socket, HelloWorld)
+            .await
+            .expect("serve_connection");
+    });
+
+    let tcp = connect_async(addr).await;
+    let (mut client, conn) = hyper::client::conn::Builder::new()
+        .http2_only(true)
+        .handshake::<_, Body>(tcp)
+        .await
+        .expect("http handshake");
+
+    tokio::spawn(async move {
+        conn.await.expect("client conn");
+    });
+
+    let req = http::Request::new(hyper::Body::empty());
+    let res = client.send_request(req).await.expect("client.send_request");
+    assert_eq!(res.headers()["x-powered-by"], "hyper");
+}
+
+async fn respond_with_default_server_header(server: Option<&'static str>) -> String {
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .expect("write 1");
+        let mut response = String::new();
+        tcp.read_to_string(&mut response).expect("read 1");
+        response
+    });
+
+    let svc = service_fn(move |_req: Request<Body>| {
+        let mut res = Response::builder();
+        if let Some(server) = server {
+            res = res.header("server", server);
+        }
+        future::ok::<_, hyper::Error>(res.body(Body::empty()).unwrap())
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .default_server_header(Some(HeaderValue::from_static("myapp/1.0")))
+        .serve_connection(socket, svc)
+        .await
+        .expect("serve_connection");
+    child.join().expect("client thread")
+}
+
+#[tokio::test]
+async fn default_server_header_is_inserted() {
+    let _ = pretty_env_logger::try_init();
+    let response = respond_with_default_server_header(None).await;
+    assert!(
+        response.contains("\r\nserver: myapp/1.0\r\n"),
+        "{:?}",
+        response
+    );
+}
+
+#[tokio::test]
+async fn default_server_header_keeps_service_header() {
+    let _ = pretty_env_logger::try_init();
+    let response = respond_with_default_server_header(Some("custom")).await;
+    assert!(
+        response.contains("\r\nserver: custom\r\n"),
+        "{:?}",
+        response
+    );
+    assert!(!response.contains("myapp"), "{:?}", response);
+}
+
+#[tokio::test]
+async fn http2_default_server_header() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.expect("accept");
+
+        Http::new()
+            .http2_only(true)
+            .default_server_header(Some(HeaderValue::from_static("myapp/1.0")))
+            .serve_connection(socket, HelloWorld)
+            .await
+            .expect("serve_connection");
+    });
+
+    let tcp = connect_async(addr).await;
+    let (mut client, conn) = hyper::client::conn::Builder::new()
+        .http2_only(true)
+        .handshake::<_, Body>(tcp)
+        .await
+        .expect("http handshake");
+
+    tokio::spawn(async move {
+        conn.await.expect("client conn");
+    });
+
+    let req = http::Request::new(hyper::Body::empty());
+    let res = client.send_request(req).await.expect("client.send_request");
+    assert_eq!(res.headers()["server"], "myapp/1.0");
+}
+
+async fn respond_with_alt_svc(alt_svc: Option<HeaderValue>) -> String {
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .expect("write 1");
+        let mut response = String::new();
+        tcp.read_to_string(&mut response).expect("read 1");
+        response
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .alt_svc(Some(HeaderValue::from_static("h2=\":8443\"")))
+        .alt_svc(alt_svc)
+        .serve_connection(socket, HelloWorld)
+        .await
+        .expect("serve_connection");
+    child.join().expect("client thread")
+}
+
+#[tokio::test]
+async fn alt_svc_header() {
+    let _ = pretty_env_logger::try_init();
+
+    let response = respond_with_alt_svc(Some(HeaderValue::from_static("h3=\":443\""))).await;
+    assert!(
+        response.contains("\r\nalt-svc: h3=\":443\"\r\n"),
+        "{:?}",
+        response
+    );
+
+    let response = respond_with_alt_svc(None).await;
+    assert!(!response.contains("alt-svc"), "{:?}", response);
+}
+
+#[tokio::test]
+async fn http2_server_push_queue_is_bounded() {
+    use hyper::ext::PushSender;
+
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.expect("accept");
+
+        let svc = service_fn(|req: Request<Body>| {
+            let push = req.extensions().get::<PushSender>().expect("PushSender");
+            let mut queued = 0;
+            loop {
+                let pushed_req = Request::get("https://localhost/style.css")
+                    .body(())
+                    .unwrap();
+                if push
+                    .push(pushed_req, Response::new(Bytes::from_static(b"body {}")))
+                    .is_err()
+                {
+                    break;
+                }
+                queued += 1;
+            }
+            future::ok::<_, hyper::Error>(Response::new(Body::from(queued.to_string())))
+        });
+
+        Http::new()
+            .http2_only(true)
+            .serve_connection(socket, svc)
+            .await
+            .expect("serve_connection");
+    });
+
+    let tcp = connect_async(addr).await;
+    let (h2, connection) = h2::client::handshake(tcp).await.unwrap();
+    tokio::spawn(async move {
+        connection.await.unwrap();
+    });
+    let mut h2 = h2.ready().await.unwrap();
+
+    let req = Request::get("https://localhost/").body(()).unwrap();
+    let (response, _) = h2.send_request(req, true).unwrap();
+    let response = response.await.expect("response");
+    let mut body = response.into_body();
+    let data = body.data().await.expect("body").unwrap();
+    assert_eq!(data, "16");
+}
+
+#[tokio::test]
+async fn http2_server_push() {
+    use hyper::ext::PushSender;
+
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.expect("accept");
+
+        let svc = service_fn(|req: Request<Body>| {
+            let push = req.extensions().get::<PushSender>().expect("PushSender");
+            let pushed_req = Request::get("https://localhost/style.css")
+                .body(())
+                .unwrap();
+            push.push(pushed_req, Response::new(Bytes::from_static(b"body {}")))
+                .expect("push");
+            future::ok::<_, hyper::Error>(Response::new(Body::from("hello")))
+        });
+
+        Http::new()
+            .http2_only(true)
+            .serve_connection(socket, svc)
+            .await
+            .expect("serve_connection");
+    });
+
+    let tcp = connect_async(addr).await;
+    // h2 only sends pushed streams once the client has limited them
+    let (h2, connection) = h2::client::Builder::new()
+        .max_concurrent_streams(100)
+        .handshake::<_, Bytes>(tcp)
+        .await
+        .unwrap();
+    tokio::spawn(async move {
+        connection.await.unwrap();
+    });
+    let mut h2 = h2.ready().await.unwrap();
+
+    let req = Request::get("https://localhost/").body(()).unwrap();
+    let (mut response, _) = h2.send_request(req, true).unwrap();
+    let mut push_promises = response.push_promises();
+
+    let response = response.await.expect("response");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let push_promise = push_promises
+        .push_promise()
+        .await
+        .expect("push promise")
+        .expect("push promise error");
+    let (pushed_req, pushed_res) = push_promise.into_parts();
+    assert_eq!(pushed_req.uri().path(), "/style.css");
+
+    let pushed_res = pushed_res.await.expect("pushed response");
+    assert_eq!(pushed_res.status(), StatusCode::OK);
+    assert_eq!(pushed_res.headers()["content-length"], "7");
+    let mut body = pushed_res.into_body();
+    let data = body.data().await.expect("pushed body").unwrap();
+    assert_eq!(data, "body {}");
+}
+
+#[tokio::test]
+async fn http1_uri_validator_rejects_with_400() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            CONNECT localhost:80 HTTP/1.1\r\n\
+            \r\n\
+        ",
+        )
+        .expect("write 1");
+        let mut buf = [0; 256];
+        tcp.read(&mut buf).expect("read 1");
+
+        let expected = "HTTP/1.1 400 Bad Request\r\n";
+        assert_eq!(s(&buf[..expected.len()]), expected);
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .http1_uri_validator(|uri| uri.scheme().is_none() && uri.authority().is_none())
+        .serve_connection(socket, unreachable_service())
+        .await
+        .expect_err("rejected uri should error");
+}
+
+#[tokio::test]
+async fn http1_require_host_header() {
+    async fn status_line(require: bool, host: bool) -> String {
+        let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let child = thread::spawn(move || {
+            let mut tcp = connect(&addr);
+            let host = if host { "Host: example.domain\r\n" } else { "" };
+            let req = format!("GET / HTTP/1.1\r\n{}Connection: close\r\n\r\n", host);
+            tcp.write_all(req.as_bytes()).expect("write 1");
+            let mut response = String::new();
+            tcp.read_to_string(&mut response).expect("read 1");
+            response.lines().next().unwrap_or_default().to_owned()
+        });
+
+        let (socket, _) = listener.accept().await.unwrap();
+        let _ = Http::new()
+            .http1_require_host_header(require)
+            .serve_connection(socket, HelloWorld)
+            .await;
+        child.join().expect("client thread")
+    }
+
+    let _ = pretty_env_logger::try_init();
+
+    assert_eq!(status_line(false, true).await, "HTTP/1.1 200 OK");
+    assert_eq!(status_line(false, false).await, "HTTP/1.1 200 OK");
+    assert_eq!(status_line(true, true).await, "HTTP/1.1 200 OK");
+    assert_eq!(status_line(true, false).await, "HTTP/1.1 400 Bad Request");
+}
+
+#[tokio::test]
+async fn http1_strict_expect() {
+    async fn status_line(strict: bool, expect: &'static str) -> String {
+        let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let child = thread::spawn(move || {
+            let mut tcp = connect(&addr);
+            let req = format!(
+                "POST / HTTP/1.1\r\n\
+                 Host: example.domain\r\n\
+                 Expect: {}\r\n\
+                 Content-Length: 5\r\n\
+                 Connection: close\r\n\
+                 \r\n",
+                expect
+            );
+            tcp.write_all(req.as_bytes()).expect("write 1");
+            let mut buf = [0; 256];
+            let n = tcp.read(&mut buf).expect("read 1");
+            if s(&buf[..n]).starts_with("HTTP/1.1 100 Continue\r\n") {
+                tcp.write_all(b"hello").expect("write 2");
+            }
+            let mut response = s(&buf[..n]).to_owned();
+            tcp.read_to_string(&mut response).expect("read 2");
+            response
+                .lines()
+                .find(|line| !line.starts_with("HTTP/1.1 100") && line.starts_with("HTTP/1.1"))
+                .unwrap_or_default()
+                .to_owned()
+        });
+
+        let svc = service_fn(|req: Request<Body>| async move {
+            let body = hyper::body::to_bytes(req.into_body()).await?;
+            assert_eq!(body, "hello");
+            Ok::<_, hyper::Error>(Response::new(Body::empty()))
+        });
+
+        let (socket, _) = listener.accept().await.unwrap();
+        let _ = Http::new()
+            .http1_strict_expect(strict)
+            .serve_connection(socket, svc)
+            .await;
+        child.join().expect("client thread")
+    }
+
+    let _ = pretty_env_logger::try_init();
+
+    assert_eq!(status_line(true, "100-continue").await, "HTTP/1.1 200 OK");
+    assert_eq!(
+        status_line(true, "gimme-cookies").await,
+        "HTTP/1.1 417 Expectation Failed"
+    );
+}
+
+#[tokio::test]
+async fn http1_preserve_header_case_echoes_original_case() {
+    async fn echo(preserve: bool) -> String {
+        let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let child = thread::spawn(move || {
+            let mut tcp = connect(&addr);
+            tcp.write_all(
+                b"\
+                GET / HTTP/1.1\r\n\
+                X-Custom-Header: value\r\n\
+                Connection: close\r\n\
+                \r\n\
+            ",
+            )
+            .expect("write 1");
+            let mut response = String::new();
+            tcp.read_to_string(&mut response).expect("read 1");
+            response
+        });
+
+        // echo the request headers back, like a proxy forwarding them
+        let svc = service_fn(|req: Request<Body>| {
+            let (parts, _) = req.into_parts();
+            let mut res = Response::new(Body::empty());
+            *res.headers_mut() = parts.headers;
+            *res.extensions_mut() = parts.extensions;
+            future::ok::<_, hyper::Error>(res)
+        });
+
+        let (socket, _) = listener.accept().await.unwrap();
+        Http::new()
+            .http1_preserve_header_case(preserve)
+            .serve_connection(socket, svc)
+            .await
+            .expect("serve_connection");
+        child.join().expect("client thread")
+    }
+
+    let _ = pretty_env_logger::try_init();
+
+    let response = echo(true).await;
+    assert!(
+        response.contains("\r\nX-Custom-Header: value\r\n"),
+        "{:?}",
+        response
+    );
+
+    let response = echo(false).await;
+    assert!(
+        response.contains("\r\nx-custom-header: value\r\n"),
+        "{:?}",
+        response
+    );
+}
+
+#[tokio::test]
+async fn upgrades_new() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (read_101_tx, read_101_rx) = oneshot::channel();
+
+    thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            GET / HTTP/1.1\r\n\
+            Upgrade: foobar\r\n\
+            Connection: upgrade\r\n\
+            \r\n\
+            eagerly optimistic\
+        ",
+        )
+        .expect("write 1");
+        let mut buf = [0; 256];
+        tcp.read(&mut buf).expect("read 1");
+
+        let response = s(&buf);
+        assert!(response.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+        assert!(!has_header(&response, "content-length"));
+        let _ = read_101_tx.send(());
+
+        let n = tcp.read(&mut buf).expect("read 2");
+        assert_eq!(s(&buf[..n]), "foo=bar");
+        tcp.write_all(b"bar=foo").expect("write 2");
+    });
+
+    let (upgrades_tx, upgrades_rx) = mpsc::channel();
+    let svc = service_fn(move |req: Request<Body>| {
+        let on_upgrade = hyper::upgrade::on(req);
+        let _ = upgrades_tx.send(on_upgrade);
+        future::ok::<_, hyper::Error>(
+            Response::builder()
+                .status(101)
+                .header("upgrade", "foobar")
+                .body(hyper::Body::empty())
+                .unwrap(),
+        )
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .serve_connection(socket, svc)
+        .with_upgrades()
+        .await
+        .unwrap();
+
+    let on_upgrade = upgrades_rx.recv().unwrap();
 
     // wait so that we don't write until other side saw 101 response
-    rx.await.unwrap();
+    read_101_rx.await.unwrap();
+
+    let upgraded = on_upgrade.await.expect("on_upgrade");
+    let parts = upgraded.downcast::<TkTcpStream>().unwrap();
+    assert_eq!(parts.read_buf, "eagerly optimistic");
 
     let mut io = parts.io;
     io.write_all(b"foo=bar").await.unwrap();
     let mut vec = vec![];
     io.read_to_end(&mut vec).await.unwrap();
-    assert_eq!(vec, b"bar=foo");
+    assert_eq!(s(&vec), "bar=foo");
 }
 
 #[tokio::test]
-async fn http_connect() {
-    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+async fn upgrades_downcast_wrapped_io() {
+    use tokio::io::AsyncWriteExt;
 
     let _ = pretty_env_logger::try_init();
     let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
     let addr = listener.local_addr().unwrap();
-    let (tx, rx) = oneshot::channel();
 
     thread::spawn(move || {
         let mut tcp = connect(&addr);
         tcp.write_all(
             b"\
-            CONNECT localhost:80 HTTP/1.1\r\n\
+            GET / HTTP/1.1\r\n\
+            Upgrade: foobar\r\n\
+            Connection: upgrade\r\n\
             \r\n\
-            eagerly optimistic\
         ",
         )
         .expect("write 1");
         let mut buf = [0; 256];
         tcp.read(&mut buf).expect("read 1");
 
-        let expected = "HTTP/1.1 200 OK\r\n";
-        assert_eq!(s(&buf[..expected.len()]), expected);
-        let _ = tx.send(());
+        let response = s(&buf);
+        assert!(response.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
 
         let n = tcp.read(&mut buf).expect("read 2");
         assert_eq!(s(&buf[..n]), "foo=bar");
-        tcp.write_all(b"bar=foo").expect("write 2");
     });
 
-    let (socket, _) = listener.accept().await.unwrap();
-    let conn = Http::new().serve_connection(
-        socket,
-        service_fn(|_| {
-            let res = Response::builder()
-                .status(200)
+    let (upgrades_tx, upgrades_rx) = mpsc::channel();
+    let svc = service_fn(move |req: Request<Body>| {
+        let on_upgrade = hyper::upgrade::on(req);
+        let _ = upgrades_tx.send(on_upgrade);
+        future::ok::<_, hyper::Error>(
+            Response::builder()
+                .status(101)
+                .header("upgrade", "foobar")
                 .body(hyper::Body::empty())
-                .unwrap();
-            future::ready(Ok::<_, hyper::Error>(res))
-        }),
-    );
+                .unwrap(),
+        )
+    });
 
-    let parts = conn.without_shutdown().await.unwrap();
-    assert_eq!(parts.read_buf, "eagerly optimistic");
+    let (socket, _) = listener.accept().await.unwrap();
+    let transport = DebugStream {
+        stream: socket,
+        _debug: (),
+    };
+    Http::new()
+        .serve_connection(transport, svc)
+        .with_upgrades()
+        .await
+        .unwrap();
 
-    // wait so that we don't write until other side saw 101 response
-    rx.await.unwrap();
+    let upgraded = upgrades_rx.recv().unwrap().await.expect("on_upgrade");
+
+    // the wrapper is the concrete type, so the inner stream doesn't match
+    let upgraded = upgraded.downcast::<TkTcpStream>().unwrap_err();
+    let parts = upgraded
+        .downcast::<DebugStream<TkTcpStream, ()>>()
+        .expect("downcast to wrapper");
+    assert!(parts.read_buf.is_empty());
 
     let mut io = parts.io;
+    assert_eq!(io.stream.peer_addr().unwrap().ip(), addr.ip());
     io.write_all(b"foo=bar").await.unwrap();
-    let mut vec = vec![];
-    io.read_to_end(&mut vec).await.unwrap();
-    assert_eq!(vec, b"bar=foo");
 }
 
 #[tokio::test]
-async fn upgrades_new() {
-    use tokio::io::{AsyncReadExt, AsyncWriteExt};
-
+async fn upgrades_keep_trailing_bytes_unparsed() {
     let _ = pretty_env_logger::try_init();
     let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
     let addr = listener.local_addr().unwrap();
-    let (read_101_tx, read_101_rx) = oneshot::channel();
+
+    // bytes after the upgrade request belong to the new protocol, even if
+    // they happen to look like another HTTP/1 request
+    const TRAILING: &[u8] = b"GET /not-http HTTP/1.1\r\n\r\n\x00\xff";
 
     thread::spawn(move || {
         let mut tcp = connect(&addr);
-        tcp.write_all(
-            b"\
+        let mut req = b"\
             GET / HTTP/1.1\r\n\
             Upgrade: foobar\r\n\
             Connection: upgrade\r\n\
             \r\n\
-            eagerly optimistic\
-        ",
-        )
-        .expect("write 1");
+        "
+        .to_vec();
+        req.extend_from_slice(TRAILING);
+        tcp.write_all(&req).expect("write 1");
         let mut buf = [0; 256];
         tcp.read(&mut buf).expect("read 1");
 
         let response = s(&buf);
         assert!(response.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
-        assert!(!has_header(&response, "content-length"));
-        let _ = read_101_tx.send(());
-
-        let n = tcp.read(&mut buf).expect("read 2");
-        assert_eq!(s(&buf[..n]), "foo=bar");
-        tcp.write_all(b"bar=foo").expect("write 2");
     });
 
+    let calls = Arc::new(AtomicUsize::new(0));
+    let calls2 = calls.clone();
     let (upgrades_tx, upgrades_rx) = mpsc::channel();
     let svc = service_fn(move |req: Request<Body>| {
+        calls2.fetch_add(1, Ordering::SeqCst);
         let on_upgrade = hyper::upgrade::on(req);
         let _ = upgrades_tx.send(on_upgrade);
         future::ok::<_, hyper::Error>(
@@ -1621,20 +3301,11 @@ async fn upgrades_new() {
         .await
         .unwrap();
 
-    let on_upgrade = upgrades_rx.recv().unwrap();
-
-    // wait so that we don't write until other side saw 101 response
-    read_101_rx.await.unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
 
-    let upgraded = on_upgrade.await.expect("on_upgrade");
+    let upgraded = upgrades_rx.recv().unwrap().await.expect("on_upgrade");
     let parts = upgraded.downcast::<TkTcpStream>().unwrap();
-    assert_eq!(parts.read_buf, "eagerly optimistic");
-
-    let mut io = parts.io;
-    io.write_all(b"foo=bar").await.unwrap();
-    let mut vec = vec![];
-    io.read_to_end(&mut vec).await.unwrap();
-    assert_eq!(s(&vec), "bar=foo");
+    assert_eq!(parts.read_buf, TRAILING);
 }
 
 #[tokio::test]
@@ -2076,6 +3747,178 @@ async fn h2_connect_empty_frames() {
         .unwrap();
 }
 
+#[tokio::test]
+async fn h2_max_concurrent_streams_queues_excess() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let conn = connect_async(addr).await;
+
+    let (release_tx, release_rx) = oneshot::channel::<()>();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let calls2 = calls.clone();
+
+    let client = tokio::spawn(async move {
+        let (h2, connection) = h2::client::handshake(conn).await.unwrap();
+        tokio::spawn(async move {
+            connection.await.unwrap();
+        });
+        let mut h2 = h2.ready().await.unwrap();
+
+        // Once the first response arrives, the server SETTINGS are known.
+        let (response1, _) = h2
+            .send_request(Request::get("/1").body(()).unwrap(), true)
+            .unwrap();
+        let mut body1 = response1.await.unwrap().into_body();
+
+        let (response2, _) = h2
+            .send_request(Request::get("/2").body(()).unwrap(), true)
+            .unwrap();
+        let mut response2 = Box::pin(response2);
+        tokio::time::timeout(Duration::from_millis(100), &mut response2)
+            .await
+            .expect_err("second stream should wait for the first");
+        assert_eq!(calls2.load(Ordering::SeqCst), 1);
+
+        // Finish the first stream, freeing up a slot.
+        drop(release_tx);
+        while let Some(chunk) = body1.data().await {
+            assert!(chunk.unwrap().is_empty());
+        }
+        let response2 = response2.await.unwrap();
+        assert_eq!(response2.status(), StatusCode::OK);
+        assert_eq!(calls2.load(Ordering::SeqCst), 2);
+    });
+
+    let release_rx = Arc::new(Mutex::new(Some(release_rx)));
+    let svc = service_fn(move |_req: Request<Body>| {
+        let n = calls.fetch_add(1, Ordering::SeqCst);
+        let body = if n == 0 {
+            let (sender, body) = Body::channel();
+            let rx = release_rx.lock().unwrap().take().unwrap();
+            tokio::spawn(async move {
+                let _ = rx.await;
+                drop(sender);
+            });
+            body
+        } else {
+            Body::empty()
+        };
+        future::ok::<_, hyper::Error>(Response::new(body))
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    tokio::spawn(
+        Http::new()
+            .http2_only(true)
+            .http2_max_concurrent_streams(1)
+            .serve_connection(socket, svc),
+    );
+
+    client.await.expect("client");
+}
+
+#[tokio::test]
+async fn h2_initial_stream_window_size() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let conn = connect_async(addr).await;
+
+    let client = tokio::spawn(async move {
+        let (h2, connection) = h2::client::handshake(conn).await.unwrap();
+        tokio::spawn(async move {
+            connection.await.unwrap();
+        });
+        let mut h2 = h2.ready().await.unwrap();
+
+        // Once a first response arrives, the server SETTINGS are known.
+        let (response, _) = h2
+            .send_request(Request::get("/").body(()).unwrap(), true)
+            .unwrap();
+        assert_eq!(response.await.unwrap().status(), StatusCode::OK);
+
+        let (response, mut send_stream) = h2
+            .send_request(Request::post("/").body(()).unwrap(), false)
+            .unwrap();
+
+        // The server's SETTINGS limit how much we may send on this stream.
+        send_stream.reserve_capacity(1_000);
+        let capacity = future::poll_fn(|cx| send_stream.poll_capacity(cx))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(capacity, 100);
+
+        send_stream
+            .send_data(Bytes::from(vec![b'x'; 100]), true)
+            .unwrap();
+        let response = response.await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    });
+
+    let svc = service_fn(|req: Request<Body>| async move {
+        if req.method() == hyper::Method::POST {
+            let body = hyper::body::to_bytes(req.into_body()).await?;
+            assert_eq!(body.len(), 100);
+        }
+        Ok::<_, hyper::Error>(Response::new(Body::empty()))
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    tokio::spawn(
+        Http::new()
+            .http2_only(true)
+            .http2_initial_stream_window_size(100)
+            .http2_initial_connection_window_size(1_000_000)
+            .serve_connection(socket, svc),
+    );
+
+    client.await.expect("client");
+}
+
+#[tokio::test]
+async fn http2_stream_id_extension() {
+    use hyper::ext::Http2StreamId;
+
+    let _ = pretty_env_logger::try_init();
+
+    async fn stream_ids(http2: bool) -> Vec<Option<u32>> {
+        let (ids_tx, ids_rx) = mpsc::channel();
+        let server = Server::bind(&([127, 0, 0, 1], 0).into())
+            .http2_only(http2)
+            .serve(make_service_fn(move |_| {
+                let ids_tx = ids_tx.clone();
+                async move {
+                    Ok::<_, BoxError>(service_fn(move |req: Request<Body>| {
+                        let id = req.extensions().get::<Http2StreamId>();
+                        let id = id.map(Http2StreamId::as_u32);
+                        ids_tx.send(id).unwrap();
+                        future::ok::<_, hyper::Error>(Response::new(Body::empty()))
+                    }))
+                }
+            }));
+        let addr = server.local_addr();
+        tokio::spawn(async move {
+            server.await.expect("server");
+        });
+
+        let client = Client::builder()
+            .http2_only(http2)
+            .build_http::<hyper::Body>();
+        for _ in 0..2 {
+            let uri = format!("http://{}", addr).parse().unwrap();
+            let res = client.get(uri).await.expect("client.get");
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+        ids_rx.try_iter().collect()
+    }
+
+    // client-initiated streams use odd identifiers
+    assert_eq!(stream_ids(true).await, vec![Some(1), Some(3)]);
+    assert_eq!(stream_ids(false).await, vec![None, None]);
+}
+
 #[tokio::test]
 async fn parse_errors_send_4xx_response() {
     let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
@@ -2108,60 +3951,199 @@ async fn illegal_request_length_returns_400_response() {
         tcp.write_all(b"POST / HTTP/1.1\r\nContent-Length: foo\r\n\r\n")
             .unwrap();
         let mut buf = [0; 256];
-        tcp.read(&mut buf).unwrap();
+        tcp.read(&mut buf).unwrap();
+
+        let expected = "HTTP/1.1 400 ";
+        assert_eq!(s(&buf[..expected.len()]), expected);
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .serve_connection(socket, HelloWorld)
+        .await
+        .expect_err("illegal Content-Length should error");
+}
+
+#[cfg(feature = "http1")]
+#[test]
+#[should_panic]
+fn max_buf_size_panic_too_small() {
+    const MAX: usize = 8191;
+    Http::new().max_buf_size(MAX);
+}
+
+#[cfg(feature = "http1")]
+#[test]
+fn max_buf_size_no_panic() {
+    const MAX: usize = 8193;
+    Http::new().max_buf_size(MAX);
+}
+
+#[cfg(feature = "http1")]
+#[test]
+fn try_max_buf_size() {
+    let err = Http::new().try_max_buf_size(8191).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "max_buf_size is smaller than the minimum that h1 specifies"
+    );
+
+    Http::new()
+        .try_max_buf_size(8192)
+        .expect("minimum is allowed");
+}
+
+#[cfg(feature = "http1")]
+#[tokio::test]
+async fn max_buf_size() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    const MAX: usize = 16_000;
+
+    thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(b"POST /").expect("write 1");
+        tcp.write_all(&[b'a'; MAX]).expect("write 2");
+        let mut buf = [0; 256];
+        tcp.read(&mut buf).expect("read 1");
+
+        let expected = "HTTP/1.1 431 ";
+        assert_eq!(s(&buf[..expected.len()]), expected);
+    });
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .max_buf_size(MAX)
+        .serve_connection(socket, HelloWorld)
+        .await
+        .expect_err("should TooLarge error");
+}
+
+#[cfg(feature = "http1")]
+#[tokio::test]
+async fn max_request_body_size_sized() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            POST / HTTP/1.1\r\n\
+            Content-Length: 10000000000\r\n\
+            \r\n\
+        ",
+        )
+        .expect("write 1");
+        let mut buf = [0; 256];
+        tcp.read(&mut buf).expect("read 1");
 
-        let expected = "HTTP/1.1 400 ";
+        let expected = "HTTP/1.1 413 Payload Too Large\r\n";
         assert_eq!(s(&buf[..expected.len()]), expected);
     });
 
     let (socket, _) = listener.accept().await.unwrap();
-    Http::new()
-        .serve_connection(socket, HelloWorld)
+    let err = Http::new()
+        .max_request_body_size(10)
+        .serve_connection(socket, unreachable_service())
         .await
-        .expect_err("illegal Content-Length should error");
-}
-
-#[cfg(feature = "http1")]
-#[test]
-#[should_panic]
-fn max_buf_size_panic_too_small() {
-    const MAX: usize = 8191;
-    Http::new().max_buf_size(MAX);
-}
-
-#[cfg(feature = "http1")]
-#[test]
-fn max_buf_size_no_panic() {
-    const MAX: usize = 8193;
-    Http::new().max_buf_size(MAX);
+        .expect_err("body too large should error");
+    assert!(err.is_body_too_large(), "{:?}", err);
 }
 
 #[cfg(feature = "http1")]
 #[tokio::test]
-async fn max_buf_size() {
+async fn max_request_body_size_chunked() {
     let _ = pretty_env_logger::try_init();
     let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
     let addr = listener.local_addr().unwrap();
 
-    const MAX: usize = 16_000;
-
     thread::spawn(move || {
         let mut tcp = connect(&addr);
-        tcp.write_all(b"POST /").expect("write 1");
-        tcp.write_all(&[b'a'; MAX]).expect("write 2");
+        tcp.write_all(
+            b"\
+            POST / HTTP/1.1\r\n\
+            Transfer-Encoding: chunked\r\n\
+            \r\n\
+            5\r\nhello\r\n\
+            A\r\n0123456789\r\n\
+            0\r\n\r\n\
+        ",
+        )
+        .expect("write 1");
         let mut buf = [0; 256];
         tcp.read(&mut buf).expect("read 1");
 
-        let expected = "HTTP/1.1 431 ";
+        let expected = "HTTP/1.1 413 Payload Too Large\r\n";
         assert_eq!(s(&buf[..expected.len()]), expected);
     });
 
     let (socket, _) = listener.accept().await.unwrap();
     Http::new()
-        .max_buf_size(MAX)
-        .serve_connection(socket, HelloWorld)
+        .max_request_body_size(10)
+        .serve_connection(
+            socket,
+            service_fn(|req: Request<Body>| async move {
+                let err = hyper::body::to_bytes(req.into_body())
+                    .await
+                    .expect_err("body should be too large");
+                assert!(err.is_body_too_large(), "{:?}", err);
+                Response::builder()
+                    .status(StatusCode::PAYLOAD_TOO_LARGE)
+                    .body(Body::empty())
+            }),
+        )
         .await
-        .expect_err("should TooLarge error");
+        .expect("serve_connection");
+}
+
+#[cfg(feature = "http1")]
+#[tokio::test]
+async fn http1_writev_false_coalesces_writes() {
+    let _ = pretty_env_logger::try_init();
+
+    async fn write_calls(writev: bool) -> usize {
+        let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let child = thread::spawn(move || {
+            let mut tcp = connect(&addr);
+            tcp.write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+                .expect("write");
+            let mut buf = Vec::new();
+            tcp.read_to_end(&mut buf).expect("read");
+            assert!(buf.starts_with(b"HTTP/1.1 200 OK\r\n"));
+            assert!(buf.ends_with(HELLO.as_bytes()));
+        });
+
+        let (socket, _) = listener.accept().await.unwrap();
+        let writes = Arc::new(AtomicUsize::new(0));
+        let socket = WriteCounter {
+            stream: socket,
+            writes: writes.clone(),
+        };
+        Http::new()
+            .http1_writev(writev)
+            .serve_connection(socket, HelloWorld)
+            .await
+            .expect("serve_connection");
+
+        child.join().expect("client thread");
+        writes.load(Ordering::SeqCst)
+    }
+
+    let queued = write_calls(true).await;
+    let flattened = write_calls(false).await;
+    assert_eq!(flattened, 1, "head and body written with one call");
+    assert!(
+        flattened < queued,
+        "flattened ({}) should use fewer writes than queued ({})",
+        flattened,
+        queued
+    );
 }
 
 #[cfg(feature = "stream")]
@@ -2189,6 +4171,33 @@ fn streaming_body() {
     assert_eq!(buf.len(), 100_789, "full streamed body read");
 }
 
+#[cfg(feature = "stream")]
+#[test]
+fn streaming_body_single_chunk_has_content_length() {
+    let _ = pretty_env_logger::try_init();
+
+    // disable keep-alive so we can use read_to_end
+    let server = serve_opts().keep_alive(false).serve();
+
+    let b = futures_util::stream::iter(vec![Ok::<_, hyper::Error>("hello world")]);
+    let b = hyper::Body::wrap_stream(b);
+    server.reply().body_stream(b);
+
+    let mut tcp = connect(server.addr());
+    tcp.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    let mut buf = Vec::new();
+    tcp.read_to_end(&mut buf).expect("read 1");
+    let response = s(&buf);
+
+    assert!(
+        response.contains("\r\ncontent-length: 11\r\n"),
+        "{:?}",
+        response
+    );
+    assert!(!response.contains("transfer-encoding"), "{:?}", response);
+    assert!(response.ends_with("\r\n\r\nhello world"), "{:?}", response);
+}
+
 #[test]
 fn http1_response_with_http2_version() {
     let server = serve();
@@ -2243,6 +4252,39 @@ fn http1_only() {
     .unwrap_err();
 }
 
+#[tokio::test]
+async fn serve_connection_with_alpn_h2_expects_preface() {
+    let _ = pretty_env_logger::try_init();
+
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = tokio::spawn(async move {
+        let mut conn = connect_async(addr).await;
+
+        // an HTTP/1 request is not a valid h2 preface
+        conn.write_all(b"GET / HTTP/1.1\r\nHost: example.domain\r\n\r\n")
+            .await
+            .expect("client write");
+
+        let mut buf = [0u8; 9];
+        conn.read_exact(&mut buf).await.expect("client read");
+        buf
+    });
+
+    let (socket, _) = listener.accept().await.expect("accept");
+
+    Http::new()
+        .http1_only(true)
+        .serve_connection_with_alpn(socket, unreachable_service(), Some(b"h2"))
+        .await
+        .expect_err("serve_connection_with_alpn should error");
+
+    // the server starts with its own SETTINGS frame
+    let frame_head = client.await.expect("client task");
+    assert_eq!(frame_head[3], 4, "frame type should be SETTINGS");
+}
+
 #[tokio::test]
 async fn http2_service_error_sends_reset_reason() {
     use std::error::Error;
@@ -2332,6 +4374,139 @@ impl tower_service::Service<Request<Body>> for Http2ReadyErrorSvc {
     }
 }
 
+#[tokio::test]
+async fn addr_incoming_sets_tcp_nodelay() {
+    use hyper::server::accept::Accept;
+    use hyper::server::conn::AddrIncoming;
+
+    let _ = pretty_env_logger::try_init();
+
+    let mut incoming = AddrIncoming::bind(&([127, 0, 0, 1], 0).into()).unwrap();
+    incoming.set_nodelay(true);
+    let addr = incoming.local_addr();
+
+    let _tcp = TkTcpStream::connect(addr).await.unwrap();
+    let stream = future::poll_fn(|cx| Pin::new(&mut incoming).poll_accept(cx))
+        .await
+        .expect("incoming closed")
+        .expect("accept");
+    assert!(stream.into_inner().nodelay().unwrap());
+}
+
+#[tokio::test]
+async fn server_on_accept_rejects_connection() {
+    use std::net::Ipv4Addr;
+
+    let _ = pretty_env_logger::try_init();
+
+    let server = Server::bind(&([127, 0, 0, 1], 0).into())
+        .on_accept(|addr| addr.ip() != Ipv4Addr::LOCALHOST)
+        .serve(make_service_fn(
+            |_| async move { Ok::<_, BoxError>(HelloWorld) },
+        ));
+    let addr = server.local_addr();
+
+    tokio::task::spawn(async move {
+        server.await.expect("server");
+    });
+
+    let mut tcp = TkTcpStream::connect(addr).await.unwrap();
+    tcp.write_all(b"GET / HTTP/1.1\r\nHost: example.domain\r\n\r\n")
+        .await
+        .unwrap();
+    let mut buf = Vec::new();
+    // The socket is dropped without a response, which may surface as either
+    // EOF or a reset.
+    let n = tokio::time::timeout(Duration::from_secs(5), tcp.read_to_end(&mut buf))
+        .await
+        .expect("connection should be closed")
+        .unwrap_or(0);
+    assert_eq!(n, 0);
+}
+
+#[tokio::test]
+async fn server_on_connection_called_per_connection() {
+    let _ = pretty_env_logger::try_init();
+
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+    let server = Server::bind(&([127, 0, 0, 1], 0).into())
+        .on_connection(move |addr| {
+            assert!(addr.ip().is_loopback());
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .serve(make_service_fn(
+            |_| async move { Ok::<_, BoxError>(HelloWorld) },
+        ));
+    let addr = server.local_addr();
+
+    tokio::task::spawn(async move {
+        server.await.expect("server");
+    });
+
+    for i in 1..=3 {
+        let mut tcp = TkTcpStream::connect(addr).await.unwrap();
+        tcp.write_all(b"GET / HTTP/1.1\r\nHost: example.domain\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut buf = Vec::new();
+        tcp.read_to_end(&mut buf).await.unwrap();
+        assert!(s(&buf).starts_with("HTTP/1.1 200 OK\r\n"));
+        assert_eq!(connections.load(Ordering::SeqCst), i);
+    }
+}
+
+#[tokio::test]
+async fn server_from_tcp_uses_prebound_listener() {
+    let _ = pretty_env_logger::try_init();
+
+    let std_listener = StdTcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = std_listener.local_addr().unwrap();
+
+    let server = Server::from_tcp(std_listener)
+        .expect("from_tcp")
+        .serve(make_service_fn(
+            |_| async move { Ok::<_, BoxError>(HelloWorld) },
+        ));
+    assert_eq!(server.local_addr(), addr);
+
+    tokio::task::spawn(async move {
+        server.await.expect("server");
+    });
+
+    let uri = format!("http://{}", addr).parse().unwrap();
+    let res = Client::new().get(uri).await.expect("client.get");
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(body, HELLO);
+}
+
+#[tokio::test]
+async fn graceful_server_local_addr_before_serving() {
+    let _ = pretty_env_logger::try_init();
+
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service_fn(|_| async move {
+        Ok::<_, BoxError>(HelloWorld)
+    }));
+
+    // the port is known as soon as the server is bound, so there's no need
+    // to wait for the server to start before connecting
+    let addr = server.local_addr();
+    assert_ne!(addr.port(), 0);
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(server.with_graceful_shutdown(async move {
+        let _ = shutdown_rx.await;
+    }));
+
+    let uri = format!("http://{}", addr).parse().unwrap();
+    let res = Client::new().get(uri).await.expect("client.get");
+    assert_eq!(res.status(), StatusCode::OK);
+
+    shutdown_tx.send(()).unwrap();
+    server.await.unwrap().expect("server");
+}
+
 #[tokio::test]
 #[ignore] // sometimes ECONNRESET wins the race
 async fn http2_service_poll_ready_error_sends_goaway() {
@@ -2369,6 +4544,89 @@ async fn http2_service_poll_ready_error_sends_goaway() {
     assert_eq!(h2_err.reason(), Some(h2::Reason::INADEQUATE_SECURITY));
 }
 
+#[tokio::test]
+async fn service_timeout_responds_with_503() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            GET / HTTP/1.1\r\n\
+            Connection: close\r\n\
+            \r\n\
+        ",
+        )
+        .expect("write 1");
+        let mut response = String::new();
+        tcp.read_to_string(&mut response).expect("read 1");
+        response
+    });
+
+    let svc = hyper::service::timeout(
+        service_fn(|_req: Request<Body>| async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok::<_, hyper::Error>(Response::new(Body::from("too late")))
+        }),
+        Duration::from_millis(50),
+    );
+
+    let (socket, _) = listener.accept().await.unwrap();
+    Http::new()
+        .serve_connection(socket, svc)
+        .await
+        .expect("serve_connection");
+
+    let response = child.join().expect("client thread");
+    assert!(
+        response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"),
+        "{:?}",
+        response
+    );
+    assert!(!response.contains("too late"), "{:?}", response);
+}
+
+#[tokio::test]
+async fn service_timeout_with_error_is_timeout() {
+    let _ = pretty_env_logger::try_init();
+    let listener = tcp_bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let child = thread::spawn(move || {
+        let mut tcp = connect(&addr);
+        tcp.write_all(
+            b"\
+            GET / HTTP/1.1\r\n\
+            \r\n\
+        ",
+        )
+        .expect("write 1");
+        let mut response = Vec::new();
+        tcp.read_to_end(&mut response).expect("read 1");
+        assert!(response.is_empty(), "{:?}", s(&response));
+    });
+
+    let svc = hyper::service::timeout(
+        service_fn(|_req: Request<Body>| async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok::<_, hyper::Error>(Response::new(Body::from("too late")))
+        }),
+        Duration::from_millis(50),
+    )
+    .with_error();
+
+    let (socket, _) = listener.accept().await.unwrap();
+    let err = Http::new()
+        .serve_connection(socket, svc)
+        .await
+        .expect_err("service call should time out");
+    assert!(err.is_timeout(), "{:?}", err);
+
+    child.join().expect("client thread");
+}
+
 #[test]
 fn skips_content_length_for_304_responses() {
     let server = serve();
@@ -3036,6 +5294,63 @@ impl<T: AsyncRead + Unpin, D: Unpin> AsyncRead for DebugStream<T, D> {
     }
 }
 
+/// An IO that claims vectored write support, but (like the default
+/// `poll_write_vectored`) only writes the first non-empty buffer per call,
+/// counting every write that reaches it.
+struct WriteCounter<T> {
+    stream: T,
+    writes: Arc<AtomicUsize>,
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for WriteCounter<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<Result<usize, io::Error>> {
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        let buf = bufs
+            .iter()
+            .find(|b| !b.is_empty())
+            .map_or(&[][..], |b| &**b);
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for WriteCounter<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
 #[derive(Clone)]
 struct Dropped(Arc<AtomicBool>);
 